@@ -53,10 +53,15 @@ use std::{
 use sysinfo::System;
 
 /// The main REPL loop, including spinning up the prompt itself.
+///
+/// `prerun_command` is run before the first prompt. If it fails, its error is reported and the
+/// REPL starts anyway, unless `prerun_strict` is set, in which case the process exits with its
+/// exit code.
 pub fn evaluate_repl(
     engine_state: &mut EngineState,
     stack: Stack,
     prerun_command: Option<Spanned<String>>,
+    prerun_strict: bool,
     load_std_lib: Option<Spanned<String>>,
     entire_start_time: Instant,
 ) -> Result<()> {
@@ -97,7 +102,7 @@ pub fn evaluate_repl(
     let temp_file = temp_dir().join(format!("{}.nu", uuid::Uuid::new_v4()));
 
     if let Some(s) = prerun_command {
-        eval_source(
+        let exit_code = eval_source(
             engine_state,
             &mut unique_stack,
             s.item.as_bytes(),
@@ -105,6 +110,13 @@ pub fn evaluate_repl(
             PipelineData::empty(),
             false,
         );
+        if prerun_strict && exit_code != 0 {
+            // there is no prompt yet to warn about running jobs at, so exit right away
+            engine_state
+                .exit_warning_given
+                .store(true, Ordering::SeqCst);
+            cleanup_exit((), engine_state, exit_code);
+        }
         engine_state.merge_env(&mut unique_stack)?;
     }
 
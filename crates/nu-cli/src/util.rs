@@ -1,11 +1,10 @@
 #![allow(clippy::byte_char_slices)]
 
 use nu_cmd_base::hook::eval_hook;
-use nu_engine::{eval_block, eval_block_with_early_return};
+use nu_engine::{get_eval_block, get_eval_block_with_early_return};
 use nu_parser::{Token, TokenContents, lex, parse, unescape_unquote_string};
 use nu_protocol::{
     PipelineData, ShellError, Span, Value,
-    debugger::WithoutDebug,
     engine::{EngineState, Stack, StateWorkingSet},
     process::check_exit_status_future,
     report_error::report_compile_error,
@@ -310,10 +309,11 @@ fn evaluate_source(
 
     engine_state.merge_delta(delta)?;
 
+    // use the debugger if one is active, e.g. when profiling startup
     let pipeline = if allow_return {
-        eval_block_with_early_return::<WithoutDebug>(engine_state, stack, &block, input)
+        get_eval_block_with_early_return(engine_state)(engine_state, stack, &block, input)
     } else {
-        eval_block::<WithoutDebug>(engine_state, stack, &block, input)
+        get_eval_block(engine_state)(engine_state, stack, &block, input)
     }?;
     let pipeline_data = pipeline.body;
 
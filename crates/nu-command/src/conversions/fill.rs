@@ -1,7 +1,7 @@
 use nu_cmd_base::input_handler::{CmdArgument, operate};
 use nu_engine::command_prelude::*;
-
-use print_positions::print_positions;
+use nu_utils::strip_ansi_unlikely;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Clone)]
 pub struct Fill;
@@ -36,6 +36,11 @@ impl Command for Fill {
         "Fill and Align."
     }
 
+    fn extra_description(&self) -> &str {
+        "The width is the displayed width, so ANSI escape sequences are not counted and wide \
+         characters count as two columns."
+    }
+
     fn signature(&self) -> nu_protocol::Signature {
         Signature::build("fill")
             .input_output_types(vec![
@@ -224,9 +229,9 @@ fn fill_string(s: &str, args: &Arguments, span: Span) -> Value {
 
 fn pad(s: &str, width: usize, pad_char: &str, alignment: FillAlignment, truncate: bool) -> String {
     // Attribution: Most of this function was taken from https://github.com/ogham/rust-pad and tweaked. Thank you!
-    // Use width instead of len for graphical display
+    // Use the displayed width instead of len, ignoring ANSI escape sequences
 
-    let cols = print_positions(s).count();
+    let cols = strip_ansi_unlikely(s).width();
 
     if cols >= width {
         if truncate {
@@ -10,7 +10,9 @@ mod metadata;
 mod metadata_access;
 mod metadata_set;
 mod profile;
+mod status;
 mod timeit;
+mod trace;
 mod util;
 mod view;
 mod view_blocks;
@@ -18,6 +20,7 @@ mod view_files;
 mod view_ir;
 mod view_source;
 mod view_span;
+mod watchdog;
 
 pub use ast::Ast;
 pub use debug_::Debug;
@@ -31,10 +34,13 @@ pub use metadata::Metadata;
 pub use metadata_access::MetadataAccess;
 pub use metadata_set::MetadataSet;
 pub use profile::DebugProfile;
+pub use status::DebugStatus;
 pub use timeit::TimeIt;
+pub use trace::DebugTrace;
 pub use view::View;
 pub use view_blocks::ViewBlocks;
 pub use view_files::ViewFiles;
 pub use view_ir::ViewIr;
 pub use view_source::ViewSource;
 pub use view_span::ViewSpan;
+pub use watchdog::DebugWatchdog;
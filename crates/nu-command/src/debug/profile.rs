@@ -1,8 +1,21 @@
 use nu_engine::{ClosureEvalOnce, command_prelude::*};
+use nu_path::expand_path_with;
 use nu_protocol::{
-    debugger::{DurationMode, Profiler, ProfilerOptions},
+    ast,
+    debugger::{
+        DurationMode, Profiler, ProfilerClock, ProfilerGranularity, ProfilerGroupBy,
+        ProfilerOptions,
+    },
     engine::Closure,
+    shell_error::io::IoError,
 };
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// File extensions supported by `--output-file`, each written with the `to` command of that name
+const OUTPUT_FORMATS: [&str; 3] = ["json", "csv", "nuon"];
 
 #[derive(Clone)]
 pub struct DebugProfile;
@@ -31,6 +44,11 @@ impl Command for DebugProfile {
                 Some('v'),
             )
             .switch("lines", "Collect line numbers", Some('l'))
+            .switch(
+                "collapse-recursion",
+                "Aggregate instructions of recursive calls into one row each",
+                Some('c'),
+            )
             .switch(
                 "duration-values",
                 "Report instruction duration as duration values rather than milliseconds",
@@ -42,7 +60,57 @@ impl Command for DebugProfile {
                 "How many blocks/closures deep to step into (default 2)",
                 Some('m'),
             )
-            .input_output_types(vec![(Type::Any, Type::table())])
+            .named(
+                "granularity",
+                SyntaxShape::String,
+                "What to record a row for: instruction (default), block or call",
+                Some('g'),
+            )
+            .named(
+                "sample",
+                SyntaxShape::Int,
+                "Only record every Nth instruction, to reduce the overhead of profiling",
+                None,
+            )
+            .switch(
+                "exclude-builtins",
+                "Leave out the calls of built-in commands, keeping only custom commands",
+                None,
+            )
+            .named(
+                "clock",
+                SyntaxShape::String,
+                "What time to measure: wall (default) or cpu",
+                None,
+            )
+            .named(
+                "top",
+                SyntaxShape::Int,
+                "Only return the given number of slowest rows",
+                Some('t'),
+            )
+            .named(
+                "by",
+                SyntaxShape::String,
+                "What each row stands for: instruction (default) or command",
+                None,
+            )
+            .named(
+                "compare",
+                SyntaxShape::Filepath,
+                "Compare the durations with a profile saved with --output-file",
+                None,
+            )
+            .named(
+                "output-file",
+                SyntaxShape::Filepath,
+                "Write the profile to this file instead of returning it, formatted by its extension (json, csv or nuon)",
+                Some('o'),
+            )
+            .input_output_types(vec![
+                (Type::Any, Type::table()),
+                (Type::Any, Type::Nothing),
+            ])
             .category(Category::Debug)
     }
 
@@ -68,6 +136,10 @@ The output can be heavily customized. By default, the following columns are incl
 - (optional) span        : Span associated with the instruction. Can be viewed via the `view span`
                            command. Enabled with the --spans flag.
 - (optional) output      : The output value of the instruction. Enabled with the --values flag.
+- (optional) count       : How many times the instruction was evaluated. Enabled with the
+                           --collapse-recursion flag.
+- (optional) max_depth   : The deepest depth the instruction was evaluated at. Enabled with the
+                           --collapse-recursion flag.
 
 To illustrate the depth and IDs, consider `debug profile { do { if true { echo 'spam' } } }`. A unique ID is generated each time an instruction is executed, and there are two levels of depth:
 
@@ -81,9 +153,9 @@ depth   id   parent_id                    source                     pc
     1    5           0   do                                           4   call            decl 7 "do", %0    
     2    6           5   true                                         0   load-literal    %1, bool(true)     
     2    7           5   if                                           1   not             %1                 
-    2    8           5   if                                           2   branch-if       %1, 5              
+    2    8           5   if                                           2   branch-if       %1 -> 5            
     2    9           5   'spam'                                       3   load-literal    %0, string("spam") 
-    2   10           5   if                                           4   jump            6                  
+    2   10           5   if                                           4   jump            -> 6               
     2   11           5   { if true { 'spam' } }                       6   return          %0                 
     1   12           0   { do { if true { 'spam' } } }                5   return          %0                 
 ```
@@ -94,6 +166,70 @@ at `if true { 'spam' }`. The id is used to identify each element. The parent_id
 instructions inside the block are being executed because of `do` (5), which in turn was spawned from
 the root `debug profile { ... }`.
 
+When profiling recursive code, every level of recursion adds more rows to the output. With the
+--collapse-recursion flag, all instructions inside an instruction which is evaluated again while it
+is still running (like the call of a recursive command) are aggregated by their source location and
+index into one row each. The id, parent_id and depth of such a row are taken from its first
+evaluation, and its duration is the sum of its outermost evaluations. Note that recursion deeper
+than --max-depth is not profiled at all.
+
+Profiling every instruction is expensive and produces a lot of output. The --granularity option
+selects coarser, cheaper profiling:
+- instruction : Every instruction is recorded (default).
+- block       : Only entered blocks are recorded, with the duration of the whole block. The pc and
+                instruction columns are left out, and --values can't be used.
+- call        : Only instructions calling a command are recorded.
+
+Instrumenting every instruction can dominate the measured time of fast code, like hot loops. With
+--sample N, only every Nth instruction is recorded, which lowers the overhead of profiling. The
+recorded instructions are timed in full, but the profile is only a sample of what was run, so
+results are approximate. Scale the durations of rows up by N to estimate totals.
+
+With --output-file, the profile is written to the given file instead of being returned, which keeps
+large profiles out of the terminal. The format is chosen by the file extension: `.json`, `.csv` or
+`.nuon`. An existing file is overwritten.
+
+With --compare, a profile saved with --output-file is compared with the current run instead. Rows
+are matched by their source and pc, and the durations of rows with the same source and pc are added
+up, since their ids differ between runs. The first row, for the whole closure, is always matched
+with the first row of the saved profile. The result has the following columns:
+- source, pc, instruction : Identify the compared code, like in the profile.
+- before                  : Total duration in the saved profile, or null if it's not in it.
+- after                   : Total duration in the current run, or null if it's not in it.
+- delta                   : The difference `after - before`. Positive if the code got slower.
+With --duration-values, the durations are duration values, otherwise the columns are named before_ms,
+after_ms and delta_ms and contain milliseconds. --compare can't be used with --output-file.
+
+With --by command, the profile has one row per called command instead, sorted by total time, which
+is usually the quickest way to find out what is slow. The rows have the following columns:
+- command : The name of the command.
+- total   : The time spent in the command, including the commands it called. Recursive calls are
+            only counted once.
+- self    : The time spent in the command itself, without the commands it called.
+- calls   : How many times the command was called.
+Without --duration-values, the durations are milliseconds in the columns total_ms and self_ms.
+Calls deeper than --max-depth are part of the self time of their caller. --by command can't be used
+with --granularity block or --compare.
+
+With --exclude-builtins, the calls of commands which are not custom commands (built-in commands,
+keywords and plugins) are left out, so the profile focuses on the code you wrote. The instructions
+of the closures they run, like the closure of `each`, are kept, with the caller of the left out
+command as their parent. With --by command, the time of the left out commands is part of the self
+time of their caller.
+
+With --top N, only the N slowest rows are returned, sorted by their duration, so a large profile
+is reduced to its hotspots. The first row, for the whole closure, is always the slowest. With --by
+command, the rows are the N commands with the highest total time. The ids and parent_ids of the
+returned rows still refer to the full profile, so a parent may be left out. --top can't be used
+with --compare.
+
+The durations are wall time by default, which includes the time spent waiting, e.g. for files,
+the network or external commands. With --clock cpu, the CPU time of the evaluating thread is
+measured instead, so waiting doesn't inflate the durations of CPU bound code. CPU time is
+measured per thread, so the work of other threads (like those of `par-each`) is not included.
+On platforms without a CPU clock for threads (Windows and some others), wall time is measured
+regardless.
+
 For a better understanding of how instructions map to source code, see the `view ir` command.
 
 Note: In some cases, the ordering of pipeline elements might not be intuitive. For example,
@@ -110,13 +246,104 @@ confusing the id/parent_id hierarchy. The --expr flag is helpful for investigati
     ) -> Result<PipelineData, ShellError> {
         let closure: Closure = call.req(engine_state, stack, 0)?;
         let collect_spans = call.has_flag(engine_state, stack, "spans")?;
-        let collect_expanded_source = call.has_flag(engine_state, stack, "expanded-source")?;
+        let collect_expanded_source = call.has_flag(engine_state, stack, "expand-source")?;
         let collect_values = call.has_flag(engine_state, stack, "values")?;
         let collect_lines = call.has_flag(engine_state, stack, "lines")?;
+        let collapse_recursion = call.has_flag(engine_state, stack, "collapse-recursion")?;
         let duration_values = call.has_flag(engine_state, stack, "duration-values")?;
+        let exclude_builtins = call.has_flag(engine_state, stack, "exclude-builtins")?;
         let max_depth = call
             .get_flag(engine_state, stack, "max-depth")?
             .unwrap_or(2);
+        let sample_rate: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "sample")?;
+        let sample_rate = match sample_rate {
+            Some(rate) => usize::try_from(rate.item)
+                .ok()
+                .filter(|rate| *rate > 0)
+                .ok_or_else(|| ShellError::InvalidValue {
+                    valid: "a positive int".into(),
+                    actual: rate.item.to_string(),
+                    span: rate.span,
+                })?,
+            None => 1,
+        };
+        let top: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "top")?;
+        let top = top
+            .map(|top| {
+                usize::try_from(top.item)
+                    .ok()
+                    .filter(|top| *top > 0)
+                    .ok_or_else(|| ShellError::InvalidValue {
+                        valid: "a positive int".into(),
+                        actual: top.item.to_string(),
+                        span: top.span,
+                    })
+            })
+            .transpose()?;
+        let granularity =
+            match call.get_flag::<Spanned<String>>(engine_state, stack, "granularity")? {
+                Some(granularity) => parse_granularity(granularity)?,
+                None => ProfilerGranularity::Instruction,
+            };
+        let clock = match call.get_flag::<Spanned<String>>(engine_state, stack, "clock")? {
+            Some(clock) => parse_clock(clock)?,
+            None => ProfilerClock::Wall,
+        };
+        let group_by = match call.get_flag::<Spanned<String>>(engine_state, stack, "by")? {
+            Some(group_by) => parse_group_by(group_by)?,
+            None => ProfilerGroupBy::Element,
+        };
+        if group_by == ProfilerGroupBy::Command && granularity == ProfilerGranularity::Block {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--by command` cannot be used with `--granularity block`".into(),
+                span: call.get_flag_span(stack, "by").expect("has flag"),
+            });
+        }
+        if collect_values && granularity == ProfilerGranularity::Block {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--values` cannot be used with `--granularity block`".into(),
+                span: call.get_flag_span(stack, "values").expect("has flag"),
+            });
+        }
+        let output_file = call
+            .get_flag::<Spanned<PathBuf>>(engine_state, stack, "output-file")?
+            .map(|path| {
+                let cwd = engine_state.cwd(Some(stack))?;
+                let format = output_format(&path.item, path.span, call.head)?;
+                Ok::<_, ShellError>((expand_path_with(path.item, cwd, true), format))
+            })
+            .transpose()?;
+
+        let compare = call
+            .get_flag::<Spanned<PathBuf>>(engine_state, stack, "compare")?
+            .map(|path| {
+                let cwd = engine_state.cwd(Some(stack))?;
+                let format = output_format(&path.item, path.span, call.head)?;
+                Ok::<_, ShellError>((expand_path_with(path.item, cwd, true), format))
+            })
+            .transpose()?;
+        if compare.is_some() && output_file.is_some() {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--output-file` cannot be used with `--compare`".into(),
+                span: call.get_flag_span(stack, "output-file").expect("has flag"),
+            });
+        }
+        if compare.is_some() && group_by == ProfilerGroupBy::Command {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--by command` cannot be used with `--compare`".into(),
+                span: call.get_flag_span(stack, "by").expect("has flag"),
+            });
+        }
+        if compare.is_some() && top.is_some() {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--top` cannot be used with `--compare`".into(),
+                span: call.get_flag_span(stack, "top").expect("has flag"),
+            });
+        }
+        // read the saved profile first, so missing or broken files are reported before profiling
+        let before = compare
+            .map(|(path, format)| read_report(engine_state, stack, &path, format, call.head))
+            .transpose()?;
 
         let duration_mode = match duration_values {
             true => DurationMode::Value,
@@ -130,9 +357,16 @@ confusing the id/parent_id hierarchy. The --expr flag is helpful for investigati
                 collect_expanded_source,
                 collect_values,
                 collect_exprs: false,
-                collect_instructions: true,
+                collect_instructions: granularity != ProfilerGranularity::Block,
                 collect_lines,
+                collapse_recursion,
                 duration_mode,
+                granularity,
+                sample_rate,
+                group_by,
+                exclude_builtins,
+                clock,
+                top,
             },
             call.span(),
         );
@@ -157,11 +391,21 @@ confusing the id/parent_id hierarchy. The --expr flag is helpful for investigati
         // Collect the output
         let _ = pipeline_data.into_value(call.span());
 
-        Ok(engine_state
+        let report = engine_state
             .deactivate_debugger()
             .map_err(lock_err)?
-            .report(engine_state, call.span())?
-            .into_pipeline_data())
+            .report(engine_state, call.span())?;
+
+        match (output_file, before) {
+            (Some((path, format)), _) => {
+                write_report(engine_state, stack, report, &path, format, call.head)?;
+                Ok(PipelineData::empty())
+            }
+            (None, Some(before)) => {
+                Ok(compare_reports(before, report, duration_mode, call.head)?.into_pipeline_data())
+            }
+            (None, None) => Ok(report.into_pipeline_data()),
+        }
     }
 
     fn examples(&self) -> Vec<Example<'_>> {
@@ -176,6 +420,289 @@ confusing the id/parent_id hierarchy. The --expr flag is helpful for investigati
                 example: "debug profile { source $nu.config-path } --max-depth 4",
                 result: None,
             },
+            Example {
+                description: "Profile a recursive command, aggregating each level of recursion",
+                example: "def fib [n: int] { if $n < 2 { $n } else { (fib ($n - 1)) + (fib ($n - 2)) } }; debug profile { fib 10 } --max-depth 100 --collapse-recursion",
+                result: None,
+            },
+            Example {
+                description: "Profile only the commands called during config evaluation",
+                example: "debug profile { source $nu.config-path } --granularity call",
+                result: None,
+            },
+            Example {
+                description: "Profile a hot loop, recording only every 100th instruction",
+                example: "debug profile --sample 100 { 1..10000 | each { $in * 2 } | math sum }",
+                result: None,
+            },
+            Example {
+                description: "Find the commands which take the most time during config evaluation",
+                example: "debug profile { source $nu.config-path } --by command --max-depth 10",
+                result: None,
+            },
+            Example {
+                description: "Find the slowest custom commands, counting built-in commands as part of their caller",
+                example: "debug profile { source $nu.config-path } --by command --exclude-builtins --max-depth 10",
+                result: None,
+            },
+            Example {
+                description: "Find the ten slowest instructions of config evaluation",
+                example: "debug profile { source $nu.config-path } --top 10",
+                result: None,
+            },
+            Example {
+                description: "Profile the CPU time of config evaluation, leaving out time spent waiting",
+                example: "debug profile { source $nu.config-path } --clock cpu",
+                result: None,
+            },
+            Example {
+                description: "Save the profile of config evaluation as CSV",
+                example: "debug profile { source $nu.config-path } --output-file profile.csv",
+                result: None,
+            },
+            Example {
+                description: "Compare config evaluation with the saved profile, slowest changes first",
+                example: "debug profile { source $nu.config-path } --compare profile.csv | sort-by delta_ms --reverse",
+                result: None,
+            },
         ]
     }
 }
+
+fn parse_granularity(granularity: Spanned<String>) -> Result<ProfilerGranularity, ShellError> {
+    match granularity.item.as_str() {
+        "instruction" => Ok(ProfilerGranularity::Instruction),
+        "block" => Ok(ProfilerGranularity::Block),
+        "call" => Ok(ProfilerGranularity::Call),
+        _ => Err(ShellError::InvalidValue {
+            valid: "one of: instruction, block, call".into(),
+            actual: granularity.item,
+            span: granularity.span,
+        }),
+    }
+}
+
+fn parse_clock(clock: Spanned<String>) -> Result<ProfilerClock, ShellError> {
+    match clock.item.as_str() {
+        "wall" => Ok(ProfilerClock::Wall),
+        "cpu" => Ok(ProfilerClock::Cpu),
+        _ => Err(ShellError::InvalidValue {
+            valid: "one of: wall, cpu".into(),
+            actual: clock.item,
+            span: clock.span,
+        }),
+    }
+}
+
+fn parse_group_by(group_by: Spanned<String>) -> Result<ProfilerGroupBy, ShellError> {
+    match group_by.item.as_str() {
+        "instruction" => Ok(ProfilerGroupBy::Element),
+        "command" => Ok(ProfilerGroupBy::Command),
+        _ => Err(ShellError::InvalidValue {
+            valid: "one of: instruction, command".into(),
+            actual: group_by.item,
+            span: group_by.span,
+        }),
+    }
+}
+
+/// Returns the format of `--output-file`, which is its extension
+fn output_format(path: &Path, path_span: Span, head: Span) -> Result<&'static str, ShellError> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+    OUTPUT_FORMATS
+        .into_iter()
+        .find(|format| format.eq_ignore_ascii_case(extension))
+        .ok_or_else(|| ShellError::IncorrectValue {
+            msg: format!(
+                "unsupported profile format, the file extension must be one of: {}",
+                OUTPUT_FORMATS.join(", ")
+            ),
+            val_span: path_span,
+            call_span: head,
+        })
+}
+
+/// Run the command `name` without arguments, like `to json`, on `input`
+fn run_format_command(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    name: &str,
+    input: Value,
+    head: Span,
+) -> Result<Value, ShellError> {
+    let decl_id = engine_state
+        .find_decl(name.as_bytes(), &[])
+        .ok_or_else(|| ShellError::CommandNotFound { span: head })?;
+    let call = ast::Call::new(head);
+    engine_state
+        .get_decl(decl_id)
+        .run(
+            engine_state,
+            stack,
+            &(&call).into(),
+            input.into_pipeline_data(),
+        )?
+        .into_value(head)
+}
+
+/// Serialize the profiler report with the `to` command of `format` and write it to `path`
+fn write_report(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    report: Value,
+    path: &Path,
+    format: &str,
+    head: Span,
+) -> Result<(), ShellError> {
+    let contents = run_format_command(engine_state, stack, &format!("to {format}"), report, head)?
+        .coerce_into_string()?;
+
+    std::fs::write(path, contents).map_err(|err| IoError::new(err, head, path.to_path_buf()))?;
+    Ok(())
+}
+
+/// Read a profiler report written by [`write_report`]
+fn read_report(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    path: &Path,
+    format: &str,
+    head: Span,
+) -> Result<Value, ShellError> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| IoError::new(err, head, path.to_path_buf()))?;
+    run_format_command(
+        engine_state,
+        stack,
+        &format!("from {format}"),
+        Value::string(contents, head),
+        head,
+    )
+}
+
+/// Identifies the code a row of a profile was created from, the same across runs.
+/// The first row, for the whole profiled closure, has no key.
+type RowKey = Option<(String, Option<i64>)>;
+
+/// Total duration in nanoseconds of each [`RowKey`] of a profile, in order of first appearance
+struct ProfileTotals {
+    order: Vec<RowKey>,
+    rows: HashMap<RowKey, (Option<String>, i64)>,
+}
+
+impl ProfileTotals {
+    fn new(report: Value, head: Span) -> Result<Self, ShellError> {
+        let mut totals = ProfileTotals {
+            order: vec![],
+            rows: HashMap::new(),
+        };
+
+        for (index, row) in report.into_list()?.into_iter().enumerate() {
+            let row_span = row.span();
+            let row = row.into_record()?;
+            let field = |name: &str| row.get(name).filter(|val| !val.is_nothing());
+
+            let duration_ns = match (field("duration_ms"), field("duration")) {
+                (Some(ms), _) => number(ms).map(|ms| (ms * 1_000_000.0) as i64),
+                (None, Some(Value::Duration { val, .. })) => Some(*val),
+                (None, Some(ns)) => number(ns).map(|ns| ns as i64),
+                (None, None) => None,
+            }
+            .ok_or_else(|| ShellError::IncorrectValue {
+                msg: "profile rows need a duration_ms or duration column".into(),
+                val_span: row_span,
+                call_span: head,
+            })?;
+
+            let key = if index == 0 {
+                None
+            } else {
+                let source = field("source")
+                    .map(|source| source.coerce_string())
+                    .transpose()?
+                    .unwrap_or_default();
+                let pc = field("pc").and_then(number).map(|pc| pc as i64);
+                Some((source, pc))
+            };
+            let instruction = field("instruction")
+                .map(|instruction| instruction.coerce_string())
+                .transpose()?;
+
+            let (_, total) = totals.rows.entry(key.clone()).or_insert_with(|| {
+                totals.order.push(key);
+                (instruction, 0)
+            });
+            *total += duration_ns;
+        }
+
+        Ok(totals)
+    }
+}
+
+/// A number in a profile, which is a string when read from CSV
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int { val, .. } => Some(*val as f64),
+        Value::Float { val, .. } => Some(*val),
+        Value::String { val, .. } => val.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Compare the total durations of the same code in two profiler reports
+fn compare_reports(
+    before: Value,
+    after: Value,
+    duration_mode: DurationMode,
+    head: Span,
+) -> Result<Value, ShellError> {
+    let before = ProfileTotals::new(before, head)?;
+    let after = ProfileTotals::new(after, head)?;
+
+    let duration = |ns: Option<i64>| match (ns, duration_mode) {
+        (Some(ns), DurationMode::Milliseconds) => Value::float(ns as f64 / 1_000_000.0, head),
+        (Some(ns), DurationMode::Value) => Value::duration(ns, head),
+        (None, _) => Value::nothing(head),
+    };
+    let (before_col, after_col, delta_col) = match duration_mode {
+        DurationMode::Milliseconds => ("before_ms", "after_ms", "delta_ms"),
+        DurationMode::Value => ("before", "after", "delta"),
+    };
+
+    let keys = after.order.iter().chain(
+        before
+            .order
+            .iter()
+            .filter(|key| !after.rows.contains_key(*key)),
+    );
+    let rows = keys
+        .map(|key| {
+            let before = before.rows.get(key);
+            let after = after.rows.get(key);
+            let instruction = after
+                .or(before)
+                .and_then(|(instruction, _)| instruction.clone());
+            let before = before.map(|(_, ns)| *ns);
+            let after = after.map(|(_, ns)| *ns);
+            let (source, pc) = match key {
+                Some((source, pc)) => (Value::string(source, head), *pc),
+                None => (Value::string("<start>", head), None),
+            };
+
+            Value::record(
+                record! {
+                    "source" => source,
+                    "pc" => pc.map_or(Value::nothing(head), |pc| Value::int(pc, head)),
+                    "instruction" => instruction
+                        .map_or(Value::nothing(head), |instruction| Value::string(instruction, head)),
+                    before_col => duration(before),
+                    after_col => duration(after),
+                    delta_col => duration(before.zip(after).map(|(before, after)| after - before)),
+                },
+                head,
+            )
+        })
+        .collect();
+
+    Ok(Value::list(rows, head))
+}
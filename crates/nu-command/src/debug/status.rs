@@ -0,0 +1,110 @@
+use nu_engine::command_prelude::*;
+
+#[derive(Clone)]
+pub struct DebugStatus;
+
+impl Command for DebugStatus {
+    fn name(&self) -> &str {
+        "debug status"
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        Signature::build("debug status")
+            .switch(
+                "reset",
+                "Deactivate the current debugger, also if its lock is poisoned",
+                Some('r'),
+            )
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .category(Category::Debug)
+    }
+
+    fn description(&self) -> &str {
+        "Show whether a debugger is active, like the ones of `debug profile` or `debug trace`."
+    }
+
+    fn extra_description(&self) -> &str {
+        r#"The result is a record with the following columns:
+- active   : Whether code is currently evaluated with a debugger.
+- kind     : The kind of the debugger, e.g. profiler, recorder or watchdog, or null if none is active.
+- poisoned : Whether the lock of the debugger is poisoned, because a thread panicked while using it.
+             Debugging commands fail until the debugger is reset.
+- counters : Counters of the debugger, like the number of profiled elements. Which counters there
+             are depends on the kind of debugger.
+
+With --reset, the debugger is deactivated first, and its poisoned lock is recovered. The status is
+shown after resetting. Resetting the debugger of a running `debug profile` or similar command cuts
+its report short."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["debugger", "profiler", "poisoned"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        _input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let head = call.head;
+
+        if call.has_flag(engine_state, stack, "reset")? {
+            engine_state.debugger.clear_poison();
+            engine_state
+                .deactivate_debugger()
+                .map_err(|_| ShellError::NushellFailed {
+                    msg: "debugger lock poisoned again while resetting".into(),
+                })?;
+        }
+
+        let active = engine_state.is_debugging();
+        let poisoned = engine_state.debugger.is_poisoned();
+        // the debugger itself is still intact when another thread panicked while holding its lock
+        let debugger = engine_state
+            .debugger
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        let kind = match active {
+            true => Value::string(debugger.name(), head),
+            false => Value::nothing(head),
+        };
+        let counters = match active {
+            true => debugger.status(head),
+            false => Record::new(),
+        };
+
+        Ok(Value::record(
+            record! {
+                "active" => Value::bool(active, head),
+                "kind" => kind,
+                "poisoned" => Value::bool(poisoned, head),
+                "counters" => Value::record(counters, head),
+            },
+            head,
+        )
+        .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Check whether a debugger is active",
+                example: "debug status",
+                result: None,
+            },
+            Example {
+                description: "See the state of the profiler while profiling",
+                example: "debug profile { debug status | print }",
+                result: None,
+            },
+            Example {
+                description: "Recover from a debugger left in a broken state",
+                example: "debug status --reset",
+                result: None,
+            },
+        ]
+    }
+}
@@ -0,0 +1,98 @@
+use nu_engine::{ClosureEvalOnce, command_prelude::*};
+use nu_protocol::{debugger::RecordingDebugger, engine::Closure};
+
+#[derive(Clone)]
+pub struct DebugTrace;
+
+impl Command for DebugTrace {
+    fn name(&self) -> &str {
+        "debug trace"
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        Signature::build("debug trace")
+            .required(
+                "closure",
+                SyntaxShape::Closure(None),
+                "The closure to trace.",
+            )
+            .switch(
+                "values",
+                "Collect the output value of each instruction",
+                Some('v'),
+            )
+            .input_output_types(vec![(Type::Any, Type::table())])
+            .category(Category::Debug)
+    }
+
+    fn description(&self) -> &str {
+        "Record every instruction evaluated in a closure."
+    }
+
+    fn extra_description(&self) -> &str {
+        r#"Unlike `debug profile`, the trace is not limited in depth and does not contain timing
+information. It lists each evaluated instruction in order of evaluation, with the following columns:
+- id          : Position of the instruction in the trace
+- depth       : Depth of the instruction. Each entered block adds one level of depth.
+- source      : Source code that generated the instruction. If the source code has multiple lines,
+                only the first line is used and `...` is appended to the end.
+- pc          : The index of the instruction within the block.
+- instruction : The pretty printed instruction being evaluated.
+- span        : Span associated with the instruction. Can be viewed via the `view span` command.
+- (optional) output : The output value of the instruction. Enabled with the --values flag.
+
+For a better understanding of how instructions map to source code, see the `view ir` command."#
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let closure: Closure = call.req(engine_state, stack, 0)?;
+        let collect_values = call.has_flag(engine_state, stack, "values")?;
+
+        let lock_err = |_| ShellError::GenericError {
+            error: "Recorder Error".to_string(),
+            msg: "could not lock debugger, poisoned mutex".to_string(),
+            span: Some(call.head),
+            help: None,
+            inner: vec![],
+        };
+
+        engine_state
+            .activate_debugger(Box::new(RecordingDebugger::new(collect_values)))
+            .map_err(lock_err)?;
+
+        let result = ClosureEvalOnce::new(engine_state, stack, closure).run_with_input(input);
+
+        // Collect the output before deactivating, so lazily evaluated streams are traced too
+        let result = result.and_then(|pipeline_data| pipeline_data.into_value(call.span()));
+
+        let debugger = engine_state.deactivate_debugger().map_err(lock_err)?;
+
+        // Return potential errors
+        result?;
+
+        Ok(debugger
+            .report(engine_state, call.span())?
+            .into_pipeline_data())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Trace the instructions evaluated in a closure",
+                example: "debug trace { if true { 'spam' } }",
+                result: None,
+            },
+            Example {
+                description: "Find which instructions produced a string",
+                example: "debug trace --values { [a b] | str upcase } | where ($it.output | describe) == string",
+                result: None,
+            },
+        ]
+    }
+}
@@ -1,5 +1,6 @@
 use nu_engine::command_prelude::*;
-use nu_protocol::{BlockId, DeclId};
+use nu_protocol::{BlockId, DeclId, ir::Instruction};
+use std::fmt::Write;
 
 #[derive(Clone)]
 pub struct ViewIr;
@@ -26,6 +27,12 @@ impl Command for ViewIr {
                 "Integer is a declaration ID rather than a block ID.",
                 Some('d'),
             )
+            .named(
+                "only",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Only show instructions with the given opcodes (e.g. call, jump).",
+                Some('o'),
+            )
             .input_output_type(Type::Nothing, Type::String)
             .category(Category::Debug)
     }
@@ -44,6 +51,12 @@ closures.
 The --decl-id option is provided to use a declaration ID instead, which can be
 found on `call` instructions. This is sometimes better than using the name, as
 the declaration may not be in scope.
+
+The --only option restricts the listing to instructions with the given opcode
+names, as they appear in the listing (e.g. `call`, `jump`, `branch-if`). The
+original instruction indices are kept, so branch targets remain meaningful.
+With --json, only the formatted instructions are filtered. Unknown opcode names
+are an error, which lists the valid ones.
 "
         .trim()
     }
@@ -58,6 +71,22 @@ the declaration may not be in scope.
         let target: Value = call.req(engine_state, stack, 0)?;
         let json = call.has_flag(engine_state, stack, "json")?;
         let is_decl_id = call.has_flag(engine_state, stack, "decl-id")?;
+        let only: Option<Vec<Spanned<String>>> = call.get_flag(engine_state, stack, "only")?;
+        if let Some(unknown) = only
+            .iter()
+            .flatten()
+            .find(|name| !Instruction::NAMES.contains(&name.item.as_str()))
+        {
+            return Err(ShellError::IncorrectValue {
+                msg: format!(
+                    "unknown opcode '{}', expected one of: {}",
+                    unknown.item,
+                    Instruction::NAMES.join(", ")
+                ),
+                val_span: unknown.span,
+                call_span: call.head,
+            });
+        }
 
         let block_id = match target {
             Value::Closure { ref val, .. } => val.block_id,
@@ -144,30 +173,56 @@ the declaration may not be in scope.
                 inner: vec![],
             })?;
 
+        let is_shown = |instruction: &Instruction| {
+            only.as_ref()
+                .is_none_or(|only| only.iter().any(|name| name.item == instruction.name()))
+        };
+
         let formatted = if json {
-            let formatted_instructions = ir_block
+            let (instruction_indices, formatted_instructions): (Vec<_>, Vec<_>) = ir_block
                 .instructions
                 .iter()
-                .map(|instruction| {
-                    instruction
-                        .display(engine_state, &ir_block.data)
-                        .to_string()
+                .enumerate()
+                .filter(|(_, instruction)| is_shown(instruction))
+                .map(|(index, instruction)| {
+                    (
+                        index,
+                        instruction
+                            .display(engine_state, &ir_block.data)
+                            .to_string(),
+                    )
                 })
-                .collect::<Vec<_>>();
+                .unzip();
 
-            serde_json::to_string_pretty(&serde_json::json!({
+            let mut json = serde_json::json!({
                 "block_id": block_id,
                 "span": block.span,
                 "ir_block": ir_block,
                 "formatted_instructions": formatted_instructions,
-            }))
-            .map_err(|err| ShellError::GenericError {
+            });
+            if only.is_some() {
+                json["instruction_indices"] = instruction_indices.into();
+            }
+
+            serde_json::to_string_pretty(&json).map_err(|err| ShellError::GenericError {
                 error: "JSON serialization failed".into(),
                 msg: err.to_string(),
                 span: Some(call.head),
                 help: None,
                 inner: vec![],
             })?
+        } else if only.is_some() {
+            let mut formatted = String::new();
+            for (index, instruction) in ir_block.instructions.iter().enumerate() {
+                if is_shown(instruction) {
+                    let _ = writeln!(
+                        formatted,
+                        "{index:-4}: {}",
+                        instruction.display(engine_state, &ir_block.data)
+                    );
+                }
+            }
+            formatted
         } else {
             format!("{}", ir_block.display(engine_state))
         };
@@ -0,0 +1,144 @@
+use nu_engine::{ClosureEvalOnce, command_prelude::*};
+use nu_protocol::{debugger::WatchdogDebugger, engine::Closure, format_duration};
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct DebugWatchdog;
+
+impl Command for DebugWatchdog {
+    fn name(&self) -> &str {
+        "debug watchdog"
+    }
+
+    fn signature(&self) -> nu_protocol::Signature {
+        Signature::build("debug watchdog")
+            .required(
+                "closure",
+                SyntaxShape::Closure(None),
+                "The closure to watch.",
+            )
+            .named(
+                "max-step",
+                SyntaxShape::Duration,
+                "How long a single instruction may run (default 1sec)",
+                Some('m'),
+            )
+            .input_output_types(vec![(Type::Any, Type::Any)])
+            .category(Category::Debug)
+    }
+
+    fn description(&self) -> &str {
+        "Interrupt a closure when a single instruction runs for too long."
+    }
+
+    fn extra_description(&self) -> &str {
+        r#"The time between two evaluated instructions is measured, and the closure is interrupted
+as soon as it exceeds `--max-step`, as if ctrl-c was pressed. This catches hangs in scripts, like
+an external command waiting for input or a `sleep` that is much longer than intended.
+
+If the closure finishes in time, its output is returned unchanged. Otherwise, an error points at
+the instruction which ran for too long.
+
+Calling a custom command only counts the time until the first instruction inside of it, so only
+the instructions which do not evaluate other instructions can time out."#
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let closure: Closure = call.req(engine_state, stack, 0)?;
+        let max_step: Option<Spanned<i64>> = call.get_flag(engine_state, stack, "max-step")?;
+
+        let max_step = match max_step {
+            Some(max_step) if max_step.item <= 0 => {
+                return Err(ShellError::IncorrectValue {
+                    msg: "the maximum step duration must be positive".into(),
+                    val_span: max_step.span,
+                    call_span: call.head,
+                });
+            }
+            Some(max_step) => Duration::from_nanos(max_step.item as u64),
+            None => Duration::from_secs(1),
+        };
+
+        let lock_err = |_| ShellError::GenericError {
+            error: "Watchdog Error".to_string(),
+            msg: "could not lock debugger, poisoned mutex".to_string(),
+            span: Some(call.head),
+            help: None,
+            inner: vec![],
+        };
+
+        let signals = engine_state.signals();
+        engine_state
+            .activate_debugger(Box::new(WatchdogDebugger::new(max_step, signals.clone())))
+            .map_err(lock_err)?;
+
+        let result = ClosureEvalOnce::new(engine_state, stack, closure).run_with_input(input);
+
+        // Collect the output before deactivating, so lazily evaluated streams are watched too
+        let result = result.and_then(|pipeline_data| pipeline_data.into_value(call.span()));
+
+        let debugger = engine_state.deactivate_debugger().map_err(lock_err)?;
+
+        let Value::Record { val: slow, .. } = debugger.report(engine_state, call.span())? else {
+            return result.map(|value| value.into_pipeline_data());
+        };
+
+        // the interrupt came from the watchdog, not from the user
+        signals.reset();
+
+        let get_int = |value: Option<&Value>| value.and_then(|value| value.as_int().ok());
+        let span = slow
+            .get("span")
+            .and_then(|span| span.as_record().ok())
+            .and_then(|span| {
+                Some(Span::new(
+                    get_int(span.get("start"))? as usize,
+                    get_int(span.get("end"))? as usize,
+                ))
+            })
+            .unwrap_or(call.head);
+        let instruction = slow
+            .get("instruction")
+            .and_then(|instruction| instruction.as_str().ok())
+            .unwrap_or_default();
+        let elapsed = slow
+            .get("elapsed")
+            .and_then(|elapsed| elapsed.as_duration().ok())
+            .unwrap_or_default();
+
+        Err(ShellError::GenericError {
+            error: "Watchdog timeout".into(),
+            msg: format!(
+                "ran for longer than {}",
+                format_duration(max_step.as_nanos() as i64)
+            ),
+            span: Some(span),
+            help: Some(format!(
+                "interrupted `{instruction}` after {}",
+                format_duration(elapsed)
+            )),
+            inner: vec![],
+        })
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Interrupt a closure when a single instruction takes longer than a second",
+                example: "debug watchdog --max-step 1sec { sleep 5sec; 'done' }",
+                result: None,
+            },
+            Example {
+                description: "Run a script, giving up when an external command hangs",
+                example: "debug watchdog --max-step 30sec { ^git fetch }",
+                result: None,
+            },
+        ]
+    }
+}
@@ -156,6 +156,9 @@ pub fn add_shell_command_context(mut engine_state: EngineState) -> EngineState {
             DebugExperimentalOptions,
             DebugInfo,
             DebugProfile,
+            DebugStatus,
+            DebugTrace,
+            DebugWatchdog,
             Explain,
             Inspect,
             Metadata,
@@ -228,6 +231,8 @@ pub fn add_shell_command_context(mut engine_state: EngineState) -> EngineState {
             StrSubstring,
             StrTrim,
             StrUpcase,
+            StrVisibleSlice,
+            StrWrapAnsi,
             Format,
             FormatDate,
             FormatDuration,
@@ -1,11 +1,47 @@
 use nu_engine::command_prelude::*;
+use nu_protocol::{Signals, ast::PathMember};
+use std::collections::HashMap;
 
 #[derive(Copy, Clone)]
 pub(crate) enum MergeStrategy {
     /// Key-value pairs present in lhs and rhs are overwritten by values in rhs
     Shallow,
     /// Records are merged recursively, otherwise same behavior as shallow
-    Deep(ListMerge),
+    Deep(DeepMerge),
+    /// Values of keys present in lhs and rhs are collected into a list, appending to lists in lhs
+    Collect,
+    /// Values in lhs are kept, only keys missing in lhs are added with their value in rhs
+    KeysOnly,
+}
+
+#[derive(Copy, Clone)]
+pub(crate) struct DeepMerge {
+    /// How lists are merged
+    pub lists: ListMerge,
+    /// Empty values in rhs (see [`is_empty`]) leave the corresponding value in lhs unchanged
+    pub skip_empty: bool,
+    /// How many levels of nested records are merged, values below are overwritten by rhs
+    pub max_depth: Option<usize>,
+    /// Extra rows of the longer table are kept when merging tables element-wise, otherwise tables
+    /// of different lengths are an error
+    pub keep_extra: bool,
+    /// Conflicting numbers are added together instead of overwritten
+    pub sum: bool,
+}
+
+impl MergeStrategy {
+    /// The strategy for merging the values inside of a record
+    fn nested(self) -> Self {
+        match self {
+            MergeStrategy::Deep(opts) => MergeStrategy::Deep(DeepMerge {
+                max_depth: opts.max_depth.map(|depth| depth.saturating_sub(1)),
+                ..opts
+            }),
+            strategy @ (MergeStrategy::Shallow
+            | MergeStrategy::Collect
+            | MergeStrategy::KeysOnly) => strategy,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -35,6 +71,66 @@ fn is_list_of_records(val: &Value) -> bool {
     }
 }
 
+/// Test whether a value is considered empty when merging with `skip_empty`.
+///
+/// Only `null`, `""`, `[]` and `{}` are empty.
+fn is_empty(val: &Value) -> bool {
+    match val {
+        Value::Nothing { .. } => true,
+        Value::String { val, .. } => val.is_empty(),
+        Value::List { vals, .. } => vals.is_empty(),
+        Value::Record { val, .. } => val.is_empty(),
+        _ => false,
+    }
+}
+
+/// Test whether a value is a number which can be summed with `sum`.
+fn is_number(val: &Value) -> bool {
+    matches!(val, Value::Int { .. } | Value::Float { .. })
+}
+
+/// Replace every value of a record, or of the records in a list, with `replacement`.
+///
+/// Used to merge only the keys of a value with [`MergeStrategy::KeysOnly`].
+pub(crate) fn replace_values(value: Value, replacement: &Value) -> Value {
+    let span = value.span();
+    match value {
+        Value::Record { val, .. } => Value::record(
+            val.into_owned()
+                .into_iter()
+                .map(|(col, _)| (col, replacement.clone()))
+                .collect(),
+            span,
+        ),
+        Value::List { vals, .. } => Value::list(
+            vals.into_iter()
+                .map(|val| replace_values(val, replacement))
+                .collect(),
+            span,
+        ),
+        other => other,
+    }
+}
+
+/// Remove the columns for which `keep` is false from a record, or from the records in a list.
+pub(crate) fn retain_columns(value: Value, keep: &impl Fn(&str) -> bool) -> Value {
+    let span = value.span();
+    match value {
+        Value::Record { val, .. } => {
+            let mut record = val.into_owned();
+            record.retain(|col, _| keep(col));
+            Value::record(record, span)
+        }
+        Value::List { vals, .. } => Value::list(
+            vals.into_iter()
+                .map(|val| retain_columns(val, keep))
+                .collect(),
+            span,
+        ),
+        other => other,
+    }
+}
+
 /// Typecheck a merge operation.
 ///
 /// Ensures that both arguments are records, tables, or lists of non-matching records.
@@ -51,49 +147,95 @@ pub(crate) fn typecheck_merge(lhs: &Value, rhs: &Value, head: Span) -> Result<()
     }
 }
 
+/// Merge `rhs` into `lhs` according to `strategy`.
+///
+/// With `case_insensitive`, record keys are matched ignoring case, keeping the casing of `lhs`.
+///
+/// Values keep their own spans: merged records and lists have the span of `lhs`, and values taken
+/// from either side are unchanged. `span` is only used for errors and newly created values.
+///
+/// `signals` is checked once per merged column and table row, so merging huge values can be
+/// interrupted.
 pub(crate) fn do_merge(
     lhs: Value,
     rhs: Value,
     strategy: MergeStrategy,
+    case_insensitive: bool,
+    signals: &Signals,
     span: Span,
 ) -> Result<Value, ShellError> {
+    let lhs_span = lhs.span();
     match (strategy, lhs, rhs) {
         // Propagate errors
         (_, Value::Error { error, .. }, _) | (_, _, Value::Error { error, .. }) => Err(*error),
-        // Shallow merge records
+        // Keep lhs value if rhs is empty
         (
-            MergeStrategy::Shallow,
+            MergeStrategy::Deep(DeepMerge {
+                skip_empty: true, ..
+            }),
+            lhs,
+            rhs,
+        ) if is_empty(&rhs) => Ok(lhs),
+        // Shallow merge records, collect their conflicting values, or only add missing keys
+        (
+            MergeStrategy::Shallow | MergeStrategy::Collect | MergeStrategy::KeysOnly,
             Value::Record { val: lhs, .. },
             Value::Record { val: rhs, .. },
         ) => Ok(Value::record(
-            merge_records(lhs.into_owned(), rhs.into_owned(), strategy, span)?,
-            span,
+            merge_records(
+                lhs.into_owned(),
+                rhs.into_owned(),
+                strategy,
+                case_insensitive,
+                signals,
+                span,
+            )?,
+            lhs_span,
         )),
-        // Deep merge records
+        // Deep merge records, unless the depth limit has been reached
         (
-            MergeStrategy::Deep(_),
+            MergeStrategy::Deep(DeepMerge { max_depth, .. }),
             Value::Record { val: lhs, .. },
             Value::Record { val: rhs, .. },
-        ) => Ok(Value::record(
-            merge_records(lhs.into_owned(), rhs.into_owned(), strategy, span)?,
-            span,
+        ) if max_depth != Some(0) => Ok(Value::record(
+            merge_records(
+                lhs.into_owned(),
+                rhs.into_owned(),
+                strategy,
+                case_insensitive,
+                signals,
+                span,
+            )?,
+            lhs_span,
         )),
         // Merge lists by appending
         (
-            MergeStrategy::Deep(ListMerge::Append),
+            MergeStrategy::Deep(DeepMerge {
+                lists: ListMerge::Append,
+                ..
+            }),
             Value::List { vals: lhs, .. },
             Value::List { vals: rhs, .. },
-        ) => Ok(Value::list(lhs.into_iter().chain(rhs).collect(), span)),
+        ) => Ok(Value::list(lhs.into_iter().chain(rhs).collect(), lhs_span)),
         // Merge lists by prepending
         (
-            MergeStrategy::Deep(ListMerge::Prepend),
+            MergeStrategy::Deep(DeepMerge {
+                lists: ListMerge::Prepend,
+                ..
+            }),
             Value::List { vals: lhs, .. },
             Value::List { vals: rhs, .. },
-        ) => Ok(Value::list(rhs.into_iter().chain(lhs).collect(), span)),
+        ) => Ok(Value::list(rhs.into_iter().chain(lhs).collect(), lhs_span)),
         // Merge lists of records elementwise (tables and non-tables)
         // Match on shallow since this might be a top-level table
         (
-            MergeStrategy::Shallow | MergeStrategy::Deep(ListMerge::Elementwise),
+            MergeStrategy::Shallow
+            | MergeStrategy::Collect
+            | MergeStrategy::KeysOnly
+            | MergeStrategy::Deep(DeepMerge {
+                lists: ListMerge::Elementwise,
+                ..
+            }),
             lhs_list @ Value::List { .. },
             rhs_list @ Value::List { .. },
         ) if is_list_of_records(&lhs_list) && is_list_of_records(&rhs_list) => {
@@ -103,38 +245,362 @@ pub(crate) fn do_merge(
             let rhs = rhs_list
                 .into_list()
                 .expect("Value matched as list above, but is not a list");
-            Ok(Value::list(merge_tables(lhs, rhs, strategy, span)?, span))
+            Ok(Value::list(
+                merge_tables(lhs, rhs, strategy, case_insensitive, signals, span)?,
+                lhs_span,
+            ))
+        }
+        // Add up numbers
+        (
+            MergeStrategy::Deep(DeepMerge { sum: true, .. }),
+            lhs @ (Value::Int { .. } | Value::Float { .. }),
+            rhs @ (Value::Int { .. } | Value::Float { .. }),
+        ) => lhs.add(span, &rhs, span),
+        // A missing counter counts as 0
+        (MergeStrategy::Deep(DeepMerge { sum: true, .. }), lhs, Value::Nothing { .. })
+            if is_number(&lhs) =>
+        {
+            Ok(lhs)
+        }
+        (MergeStrategy::Deep(DeepMerge { sum: true, .. }), Value::Nothing { .. }, rhs)
+            if is_number(&rhs) =>
+        {
+            Ok(rhs)
+        }
+        // Numbers can only be added to numbers
+        (MergeStrategy::Deep(DeepMerge { sum: true, .. }), lhs, rhs)
+            if is_number(&lhs) != is_number(&rhs) =>
+        {
+            Err(ShellError::TypeMismatch {
+                err_message: format!("cannot sum {} and {}", lhs.get_type(), rhs.get_type()),
+                span: rhs.span(),
+            })
         }
         // Use rhs value (shallow record merge, overwrite list merge, and general scalar merge)
         (_, _, val) => Ok(val),
     }
 }
 
+/// Describe what merging `rhs` into `lhs` according to `strategy` would do, without merging.
+///
+/// Returns a table with the `column` of every column in `rhs`, and the `change` merging it would
+/// make: `added` if it is not in `lhs`, otherwise `overwritten` or `unchanged` depending on the
+/// values, `collected` with [`MergeStrategy::Collect`] and `kept` with [`MergeStrategy::KeysOnly`].
+/// Tables are checked row by row, with the index of each row in a `row` column. Like
+/// [`merge_tables`], rows of `rhs` beyond the length of `lhs` are ignored.
+///
+/// Only shallow merges can be checked, `lhs` and `rhs` must already be typechecked.
+pub(crate) fn check_merge(
+    lhs: &Value,
+    rhs: &Value,
+    strategy: MergeStrategy,
+    case_insensitive: bool,
+    signals: &Signals,
+    span: Span,
+) -> Result<Value, ShellError> {
+    let report = match (lhs, rhs) {
+        (Value::Record { val: lhs, .. }, Value::Record { val: rhs, .. }) => {
+            check_records(lhs, rhs, strategy, case_insensitive, signals, span)?
+                .into_iter()
+                .map(|(column, change)| {
+                    Value::record(
+                        record! {
+                            "column" => Value::string(column, span),
+                            "change" => Value::string(change, span),
+                        },
+                        span,
+                    )
+                })
+                .collect()
+        }
+        (Value::List { vals: lhs, .. }, Value::List { vals: rhs, .. }) => {
+            let mut report = vec![];
+            for (row, (lhs, rhs)) in lhs.iter().zip(rhs).enumerate() {
+                let changes = check_records(
+                    lhs.as_record()?,
+                    rhs.as_record()?,
+                    strategy,
+                    case_insensitive,
+                    signals,
+                    span,
+                )?;
+                report.extend(changes.into_iter().map(|(column, change)| {
+                    Value::record(
+                        record! {
+                            "row" => Value::int(row as i64, span),
+                            "column" => Value::string(column, span),
+                            "change" => Value::string(change, span),
+                        },
+                        span,
+                    )
+                }));
+            }
+            report
+        }
+        (Value::Error { error, .. }, _) | (_, Value::Error { error, .. }) => {
+            return Err(*error.clone());
+        }
+        (lhs, rhs) => {
+            return Err(ShellError::OnlySupportsThisInputType {
+                exp_input_type: "input and argument to be both record or both table".into(),
+                wrong_type: format!("{} and {}", lhs.get_type(), rhs.get_type()),
+                dst_span: span,
+                src_span: lhs.span(),
+            });
+        }
+    };
+    Ok(Value::list(report, span))
+}
+
+/// The change merging each column of `rhs` into `lhs` would make, see [`check_merge`]
+fn check_records(
+    lhs: &Record,
+    rhs: &Record,
+    strategy: MergeStrategy,
+    case_insensitive: bool,
+    signals: &Signals,
+    span: Span,
+) -> Result<Vec<(String, &'static str)>, ShellError> {
+    let rhs = if case_insensitive {
+        match_key_case(lhs, rhs.clone())
+    } else {
+        rhs.clone()
+    };
+    rhs.into_iter()
+        .map(|(col, rval)| {
+            signals.check(&span)?;
+            let change = match (lhs.get(&col), strategy) {
+                (None, _) => "added",
+                (Some(_), MergeStrategy::Collect) => "collected",
+                (Some(_), MergeStrategy::KeysOnly) => "kept",
+                (Some(lval), _) if *lval == rval => "unchanged",
+                (Some(_), _) => "overwritten",
+            };
+            Ok((col, change))
+        })
+        .collect()
+}
+
+/// Deep merge `rhs` into the value at `path` in `lhs`.
+///
+/// Missing keys along the path are created as empty records. Errors if the path goes through a
+/// value which is not a record.
+pub(crate) fn merge_at(
+    lhs: Value,
+    path: &[PathMember],
+    rhs: Value,
+    signals: &Signals,
+    span: Span,
+) -> Result<Value, ShellError> {
+    let Some((member, rest)) = path.split_first() else {
+        let strategy = MergeStrategy::Deep(DeepMerge {
+            lists: ListMerge::Elementwise,
+            skip_empty: false,
+            max_depth: None,
+            keep_extra: true,
+            sum: false,
+        });
+        return do_merge(lhs, rhs, strategy, false, signals, span);
+    };
+
+    signals.check(&span)?;
+
+    let (key, member_span) = match member {
+        PathMember::String { val, span, .. } => (val, *span),
+        PathMember::Int {
+            span: member_span, ..
+        } => {
+            return Err(ShellError::IncorrectValue {
+                msg: "Only record keys can be used in the path to merge at".into(),
+                val_span: *member_span,
+                call_span: span,
+            });
+        }
+    };
+
+    let lhs_span = lhs.span();
+    match lhs {
+        Value::Record { val, .. } => {
+            let mut record = val.into_owned();
+            let value = match record.get_mut(key) {
+                Some(inner) => std::mem::replace(inner, Value::nothing(span)),
+                None => Value::record(Record::new(), span),
+            };
+            let value = merge_at(value, rest, rhs, signals, span)?;
+            record.insert(key, value);
+            Ok(Value::record(record, lhs_span))
+        }
+        Value::Error { error, .. } => Err(*error),
+        other => Err(ShellError::IncompatiblePathAccess {
+            type_name: other.get_type().to_string(),
+            span: member_span,
+        }),
+    }
+}
+
+/// Merge the records of the list `patches` into `lhs` one after the other with `merge`, so later
+/// records win on conflicts.
+///
+/// For example:
+/// lhs = {a: 1, b: 1}
+/// patches = [{a: 2}, {a: 3, c: 3}]
+/// output = {a: 3, b: 1, c: 3}
+pub(crate) fn fold_merge(
+    lhs: Value,
+    patches: Value,
+    mut merge: impl FnMut(Value, Value) -> Result<Value, ShellError>,
+) -> Result<Value, ShellError> {
+    let Value::List { vals, .. } = patches else {
+        return Err(ShellError::TypeMismatch {
+            err_message: format!(
+                "`--fold` needs a list of records, not {}",
+                patches.get_type()
+            ),
+            span: patches.span(),
+        });
+    };
+
+    vals.into_iter()
+        .enumerate()
+        .try_fold(lhs, |merged, (index, patch)| match patch {
+            Value::Record { .. } => merge(merged, patch),
+            Value::Error { error, .. } => Err(*error),
+            other => Err(ShellError::TypeMismatch {
+                err_message: format!(
+                    "`--fold` needs a list of records, but element {index} is {}",
+                    other.get_type()
+                ),
+                span: other.span(),
+            }),
+        })
+}
+
+/// Pair up the elements of the lists `lhs` and `rhs`, which must have the same length.
+///
+/// For example:
+/// lhs = [1, 2]
+/// rhs = [10, 20]
+/// output = [[1, 10], [2, 20]]
+pub(crate) fn zip_lists(
+    lhs: Value,
+    rhs: Value,
+    signals: &Signals,
+    span: Span,
+) -> Result<Value, ShellError> {
+    let (lhs_span, rhs_span) = (lhs.span(), rhs.span());
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Value::List { vals: lhs, .. }, Value::List { vals: rhs, .. }) => (lhs, rhs),
+        (Value::Error { error, .. }, _) | (_, Value::Error { error, .. }) => return Err(*error),
+        (lhs, rhs) => {
+            return Err(ShellError::OnlySupportsThisInputType {
+                exp_input_type: "input and argument to be both list".into(),
+                wrong_type: format!("{} and {}", lhs.get_type(), rhs.get_type()),
+                dst_span: span,
+                src_span: lhs_span,
+            });
+        }
+    };
+
+    if lhs.len() != rhs.len() {
+        return Err(ShellError::IncorrectValue {
+            msg: format!(
+                "the argument has {} elements, but the input has {}",
+                rhs.len(),
+                lhs.len()
+            ),
+            val_span: rhs_span,
+            call_span: span,
+        });
+    }
+
+    lhs.into_iter()
+        .zip(rhs)
+        .map(|(lval, rval)| {
+            signals.check(&span)?;
+            Ok(Value::list(vec![lval, rval], span))
+        })
+        .collect::<Result<_, _>>()
+        .map(|pairs| Value::list(pairs, span))
+}
+
 /// Merge right-hand table into left-hand table, element-wise
 ///
 /// For example:
 /// lhs = [{a: 12, b: 34}]
 /// rhs = [{a: 56, c: 78}]
 /// output = [{a: 56, b: 34, c: 78}]
+///
+/// With a deep merge, tables of different lengths are an error unless `keep_extra` is set, in
+/// which case the extra rows of the longer table are kept unchanged. Other strategies keep the
+/// extra rows of lhs and ignore the extra rows of rhs.
 fn merge_tables(
     lhs: Vec<Value>,
-    rhs: Vec<Value>,
+    mut rhs: Vec<Value>,
     strategy: MergeStrategy,
+    case_insensitive: bool,
+    signals: &Signals,
     span: Span,
 ) -> Result<Vec<Value>, ShellError> {
+    let extra_rhs = match strategy {
+        MergeStrategy::Deep(DeepMerge { keep_extra, .. }) if lhs.len() != rhs.len() => {
+            if !keep_extra {
+                return Err(ShellError::GenericError {
+                    error: "Tables have different lengths".into(),
+                    msg: format!(
+                        "cannot merge a table with {} rows into a table with {} rows",
+                        rhs.len(),
+                        lhs.len()
+                    ),
+                    span: Some(span),
+                    help: Some(
+                        "use --keep-extra to keep the extra rows of the longer table".into(),
+                    ),
+                    inner: vec![],
+                });
+            }
+            rhs.split_off(lhs.len().min(rhs.len()))
+        }
+        _ => vec![],
+    };
     let mut table_iter = rhs.into_iter();
 
-    lhs.into_iter()
-        .map(move |inp| match (inp.into_record(), table_iter.next()) {
-            (Ok(rec), Some(to_merge)) => match to_merge.into_record() {
-                Ok(to_merge) => Ok(Value::record(
-                    merge_records(rec.to_owned(), to_merge.to_owned(), strategy, span)?,
-                    span,
-                )),
-                Err(error) => Ok(Value::error(error, span)),
-            },
-            (Ok(rec), None) => Ok(Value::record(rec, span)),
-            (Err(error), _) => Ok(Value::error(error, span)),
+    let merged: Vec<Value> = lhs
+        .into_iter()
+        .map(move |inp| {
+            signals.check(&span)?;
+            let row_span = inp.span();
+            match (inp.into_record(), table_iter.next()) {
+                (Ok(rec), Some(to_merge)) => match to_merge.into_record() {
+                    Ok(to_merge) => Ok(Value::record(
+                        merge_records(
+                            rec.to_owned(),
+                            to_merge.to_owned(),
+                            strategy,
+                            case_insensitive,
+                            signals,
+                            span,
+                        )?,
+                        row_span,
+                    )),
+                    Err(error) => Ok(Value::error(error, span)),
+                },
+                (Ok(rec), None) => Ok(Value::record(rec, row_span)),
+                (Err(error), _) => Ok(Value::error(error, span)),
+            }
+        })
+        .collect::<Result<_, ShellError>>()?;
+
+    Ok(merged.into_iter().chain(extra_rhs).collect())
+}
+
+/// Rename the keys of `rhs` to the casing of the key in `lhs` which matches them ignoring case
+fn match_key_case(lhs: &Record, rhs: Record) -> Record {
+    let keys: HashMap<String, &String> =
+        lhs.columns().map(|col| (col.to_lowercase(), col)).collect();
+    rhs.into_iter()
+        .map(|(col, val)| match keys.get(&col.to_lowercase()) {
+            Some(&key) => (key.clone(), val),
+            None => (col, val),
         })
         .collect()
 }
@@ -143,16 +609,49 @@ fn merge_records(
     mut lhs: Record,
     rhs: Record,
     strategy: MergeStrategy,
+    case_insensitive: bool,
+    signals: &Signals,
     span: Span,
 ) -> Result<Record, ShellError> {
+    let rhs = if case_insensitive {
+        match_key_case(&lhs, rhs)
+    } else {
+        rhs
+    };
     match strategy {
         MergeStrategy::Shallow => {
             for (col, rval) in rhs.into_iter() {
+                signals.check(&span)?;
                 lhs.insert(col, rval);
             }
         }
+        MergeStrategy::Collect => {
+            for (col, rval) in rhs.into_iter() {
+                signals.check(&span)?;
+                match lhs.get_mut(&col) {
+                    Some(Value::List { vals, .. }) => vals.push(rval),
+                    Some(lval) => {
+                        let lval = std::mem::replace(lval, Value::nothing(span));
+                        lhs.insert(col, Value::list(vec![lval, rval], span));
+                    }
+                    None => {
+                        lhs.insert(col, rval);
+                    }
+                }
+            }
+        }
+        MergeStrategy::KeysOnly => {
+            for (col, rval) in rhs.into_iter() {
+                signals.check(&span)?;
+                if !lhs.contains(&col) {
+                    lhs.push(col, rval);
+                }
+            }
+        }
         strategy => {
             for (col, rval) in rhs.into_iter() {
+                signals.check(&span)?;
+
                 // in order to both avoid cloning (possibly nested) record values and maintain the ordering of record keys, we can swap a temporary value into the source record.
                 // if we were to remove the value, the ordering would be messed up as we might not insert back into the original index
                 // it's okay to swap a temporary value in, since we know it will be replaced by the end of the function call
@@ -163,7 +662,14 @@ fn merge_records(
                 };
 
                 let value = match lhs.insert(&col, Value::error(failed_error, span)) {
-                    Some(lval) => do_merge(lval, rval, strategy, span)?,
+                    Some(lval) => do_merge(
+                        lval,
+                        rval,
+                        strategy.nested(),
+                        case_insensitive,
+                        signals,
+                        span,
+                    )?,
                     None => rval,
                 };
 
@@ -173,3 +679,83 @@ fn merge_records(
     }
     Ok(lhs)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, atomic::AtomicBool};
+
+    #[test]
+    fn interrupted_merge_errors() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(true)));
+        let lhs = Value::test_record(record! { "a" => Value::test_int(1) });
+        let rhs = Value::test_record(record! { "b" => Value::test_int(2) });
+
+        for strategy in [
+            MergeStrategy::Shallow,
+            MergeStrategy::Collect,
+            MergeStrategy::KeysOnly,
+            MergeStrategy::Deep(DeepMerge {
+                lists: ListMerge::Elementwise,
+                skip_empty: false,
+                max_depth: None,
+                keep_extra: true,
+                sum: false,
+            }),
+        ] {
+            let result = do_merge(
+                lhs.clone(),
+                rhs.clone(),
+                strategy,
+                false,
+                &signals,
+                Span::test_data(),
+            );
+            assert!(matches!(result, Err(ShellError::Interrupted { .. })));
+        }
+    }
+
+    #[test]
+    fn interrupted_table_merge_errors() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(true)));
+        let lhs = Value::test_list(vec![Value::test_record(record! {
+            "a" => Value::test_int(1)
+        })]);
+        let rhs = Value::test_list(vec![Value::test_record(record! {
+            "a" => Value::test_int(2)
+        })]);
+
+        let result = do_merge(
+            lhs,
+            rhs,
+            MergeStrategy::Shallow,
+            false,
+            &signals,
+            Span::test_data(),
+        );
+        assert!(matches!(result, Err(ShellError::Interrupted { .. })));
+    }
+
+    #[test]
+    fn untriggered_merge_succeeds() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        let lhs = Value::test_record(record! { "a" => Value::test_int(1) });
+        let rhs = Value::test_record(record! { "b" => Value::test_int(2) });
+
+        let result = do_merge(
+            lhs,
+            rhs,
+            MergeStrategy::Shallow,
+            false,
+            &signals,
+            Span::test_data(),
+        );
+        assert_eq!(
+            result.ok(),
+            Some(Value::test_record(record! {
+                "a" => Value::test_int(1),
+                "b" => Value::test_int(2),
+            }))
+        );
+    }
+}
@@ -1,5 +1,6 @@
-use super::common::{ListMerge, MergeStrategy, do_merge, typecheck_merge};
+use super::common::{DeepMerge, ListMerge, MergeStrategy, do_merge, fold_merge, typecheck_merge};
 use nu_engine::command_prelude::*;
+use std::num::NonZeroUsize;
 
 #[derive(Clone)]
 pub struct MergeDeep;
@@ -17,13 +18,29 @@ impl Command for MergeDeep {
         r#"The way that key-value pairs which exist in both the input and the argument are merged depends on their types.
 
 Scalar values (like numbers and strings) in the input are overwritten by the corresponding value from the argument.
+With `--sum`, numbers are added to the corresponding number from the argument instead. Summing a number with a
+value which is not a number is an error, except for null, which counts as 0. Other values are still
+overwritten.
 Records in the input are merged similarly to the merge command, but recursing rather than overwriting inner records.
 
 The way lists and tables are merged is controlled by the `--strategy` flag:
   - table: Merges tables element-wise, similarly to the merge command. Non-table lists are overwritten.
   - overwrite: Lists and tables are overwritten with their corresponding value from the argument, similarly to scalars.
   - append: Lists and tables in the input are appended with the corresponding list from the argument.
-  - prepend: Lists and tables in the input are prepended with the corresponding list from the argument."#
+  - prepend: Lists and tables in the input are prepended with the corresponding list from the argument.
+
+With `--skip-empty`, empty values in the argument do not overwrite the corresponding value in the input.
+Only null, empty strings (""), empty lists ([]) and empty records ({}) are considered empty. Empty values
+for keys which are not present in the input are still added.
+
+Tables which are merged element-wise must have the same number of rows. With `--keep-extra`, the
+extra rows of the longer table are kept unchanged instead.
+
+The `--max-depth` flag limits how many levels of nested records are merged. Values nested deeper than
+that are overwritten with the corresponding value from the argument, rather than merged.
+
+With `--fold`, the argument is a list of records which are deep merged into the input one after the
+other, so later records win on conflicts. This layers configurations without a `reduce`."#
     }
 
     fn signature(&self) -> nu_protocol::Signature {
@@ -57,6 +74,32 @@ The way lists and tables are merged is controlled by the `--strategy` flag:
                         "prepend",
                     ])),
             )
+            .named(
+                "max-depth",
+                SyntaxShape::Int,
+                "How many levels of nested records to merge (default unlimited)",
+                Some('d'),
+            )
+            .switch(
+                "keep-extra",
+                "Keep the extra rows when merging tables of different lengths element-wise",
+                None,
+            )
+            .switch(
+                "sum",
+                "Add up numbers in matching columns instead of overwriting them",
+                None,
+            )
+            .switch(
+                "fold",
+                "Deep merge each record of a list into the input, in order",
+                None,
+            )
+            .switch(
+                "skip-empty",
+                "Keep values in the input where the argument has an empty value (null, \"\", [], {})",
+                None,
+            )
     }
 
     fn examples(&self) -> Vec<Example<'_>> {
@@ -87,8 +130,8 @@ The way lists and tables are merged is controlled by the `--strategy` flag:
                 })])),
             },
             Example {
-                example: r#"{inner: [{a: 1}, {b: 2}]} | merge deep {inner: [{c: 3}]}"#,
-                description: "Merge two records and their inner tables",
+                example: r#"{inner: [{a: 1}, {b: 2}]} | merge deep --keep-extra {inner: [{c: 3}]}"#,
+                description: "Merge two records and their inner tables, keeping extra rows",
                 result: Some(Value::test_record(record! {
                     "inner" => Value::test_list(vec![
                         Value::test_record(record! {
@@ -118,6 +161,47 @@ The way lists and tables are merged is controlled by the `--strategy` flag:
                     ])
                 })),
             },
+            Example {
+                example: "{a: {b: {c: 1, d: 2}}} | merge deep {a: {b: {c: 3}}} --max-depth 2",
+                description: "Merge two records, overwriting records nested more than two levels deep",
+                result: Some(Value::test_record(record! {
+                    "a" => Value::test_record(record! {
+                        "b" => Value::test_record(record! {
+                            "c" => Value::test_int(3),
+                        }),
+                    }),
+                })),
+            },
+            Example {
+                example: "{hits: 3, misses: {total: 1.5}, name: a} | merge deep --sum {hits: 5, misses: {total: 1}, name: b}",
+                description: "Merge two records, adding up their numbers",
+                result: Some(Value::test_record(record! {
+                    "hits" => Value::test_int(8),
+                    "misses" => Value::test_record(record! {
+                        "total" => Value::test_float(2.5),
+                    }),
+                    "name" => Value::test_string("b"),
+                })),
+            },
+            Example {
+                example: r#"{name: "nu", tags: [shell]} | merge deep {name: "", tags: [], version: 1} --skip-empty"#,
+                description: "Merge two records, ignoring empty values in the argument",
+                result: Some(Value::test_record(record! {
+                    "name" => Value::test_string("nu"),
+                    "tags" => Value::test_list(vec![Value::test_string("shell")]),
+                    "version" => Value::test_int(1),
+                })),
+            },
+            Example {
+                example: "{server: {port: 80, host: localhost}} | merge deep --fold [{server: {port: 8080}} {server: {host: example.com}}]",
+                description: "Layer several configurations on top of a base configuration",
+                result: Some(Value::test_record(record! {
+                    "server" => Value::test_record(record! {
+                        "port" => Value::test_int(8080),
+                        "host" => Value::test_string("example.com"),
+                    }),
+                })),
+            },
         ]
     }
 
@@ -131,17 +215,24 @@ The way lists and tables are merged is controlled by the `--strategy` flag:
         let head = call.head;
         let merge_value: Value = call.req(engine_state, stack, 0)?;
         let strategy_flag: Option<String> = call.get_flag(engine_state, stack, "strategy")?;
+        let skip_empty = call.has_flag(engine_state, stack, "skip-empty")?;
+        let keep_extra = call.has_flag(engine_state, stack, "keep-extra")?;
+        let sum = call.has_flag(engine_state, stack, "sum")?;
+        let fold = call.has_flag(engine_state, stack, "fold")?;
+        let max_depth = call
+            .get_flag::<NonZeroUsize>(engine_state, stack, "max-depth")?
+            .map(NonZeroUsize::get);
         let metadata = input.metadata();
 
         // collect input before typechecking, so tables are detected as such
         let input_span = input.span().unwrap_or(head);
         let input = input.into_value(input_span)?;
 
-        let strategy = match strategy_flag.as_deref() {
-            None | Some("table") => MergeStrategy::Deep(ListMerge::Elementwise),
-            Some("append") => MergeStrategy::Deep(ListMerge::Append),
-            Some("prepend") => MergeStrategy::Deep(ListMerge::Prepend),
-            Some("overwrite") => MergeStrategy::Deep(ListMerge::Overwrite),
+        let lists = match strategy_flag.as_deref() {
+            None | Some("table") => ListMerge::Elementwise,
+            Some("append") => ListMerge::Append,
+            Some("prepend") => ListMerge::Prepend,
+            Some("overwrite") => ListMerge::Overwrite,
             Some(_) => {
                 return Err(ShellError::IncorrectValue {
                     msg: "The list merging strategy must be one one of: table, overwrite, append, prepend".to_string(),
@@ -150,10 +241,32 @@ The way lists and tables are merged is controlled by the `--strategy` flag:
                 })
             }
         };
+        let strategy = MergeStrategy::Deep(DeepMerge {
+            lists,
+            skip_empty,
+            max_depth,
+            keep_extra,
+            sum,
+        });
+
+        if fold {
+            let merged = fold_merge(input, merge_value, |merged, patch| {
+                typecheck_merge(&merged, &patch, head)?;
+                do_merge(merged, patch, strategy, false, engine_state.signals(), head)
+            })?;
+            return Ok(merged.into_pipeline_data_with_metadata(metadata));
+        }
 
         typecheck_merge(&input, &merge_value, head)?;
 
-        let merged = do_merge(input, merge_value, strategy, head)?;
+        let merged = do_merge(
+            input,
+            merge_value,
+            strategy,
+            false,
+            engine_state.signals(),
+            head,
+        )?;
         Ok(merged.into_pipeline_data_with_metadata(metadata))
     }
 }
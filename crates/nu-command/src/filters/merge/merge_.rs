@@ -1,5 +1,9 @@
-use super::common::{MergeStrategy, do_merge, typecheck_merge};
-use nu_engine::command_prelude::*;
+use super::common::{
+    MergeStrategy, check_merge, do_merge, fold_merge, merge_at, replace_values, retain_columns,
+    typecheck_merge, zip_lists,
+};
+use nu_engine::{ClosureEval, ClosureEvalOnce, column::get_columns, command_prelude::*};
+use nu_glob::{MatchOptions, Pattern};
 
 #[derive(Clone)]
 pub struct Merge;
@@ -18,7 +22,56 @@ impl Command for Merge {
 
 When merging tables, row 0 of the input table is overwritten
 with values from row 0 of the provided table, then
-repeating this process with row 1, and so on."#
+repeating this process with row 1, and so on.
+
+With `--collect`, values of columns present in both the input and the argument are collected
+into a list instead of being overwritten. If the input value already is a list, the new value is
+appended to it, so that many records can be folded into one.
+
+With `--keys-only`, only the columns of the argument are merged, not their values. Columns which
+are already in the input keep their value, and new columns are set to null, or to the value of
+`--default` if it is given.
+
+With `--zip`, the input and the argument are lists of the same length, and each element of the
+input is paired up with the element at the same position in the argument. Unlike `zip`, it is
+an error if the lengths differ.
+
+With `--case-insensitive`, columns are matched ignoring case, which is useful for environment
+variables on Windows (`PATH` and `Path` are the same variable). The casing of the input's column is
+kept, while the value is taken from the argument.
+
+With `--only`, only the columns of the argument matching the glob pattern are merged, and with
+`--except`, the columns matching it are left out. The other columns of the argument are ignored,
+while all the columns of the input are kept. Patterns ignore case with `--case-insensitive`.
+
+With `--strict`, it is an error if the argument has columns which are not already in the input,
+which catches misspelled columns when updating known values.
+
+With `--check`, nothing is merged. Instead, a table lists every column of the argument and the
+change merging it would make: `added`, `overwritten`, `unchanged`, `collected` (with `--collect`)
+or `kept` (with `--keys-only`). Tables get a `row` column with the index of the row. All the errors
+of a real merge are still reported, so this can be used to validate a merge before applying it.
+
+The value may also be a closure, which is called with each row of the input table (or with the
+input record) and returns the record to merge into it. The other flags apply to each of these
+merges, except `--at`, `--zip` and `--check`. A closure returning `null` leaves the row unchanged.
+
+With `--fold`, the value is a list of records which are merged into the input one after the other,
+so later records win on conflicts. The other flags apply to each of these merges, except `--at`,
+`--zip` and `--check`. Use `merge deep --fold` to deep merge them instead.
+
+Merging `null` returns the input unchanged, so optional values can be merged without checking
+for them first.
+
+The metadata of the input, like the file it was opened from and its content type, is kept on the
+merged result. The argument never changes it, regardless of where the merged value came from.
+
+With `--at`, the value is instead deep merged into the input at the given cell path, creating
+missing records along the way. This makes it easy to update a single nested value. The path
+may only contain record keys, and it is an error if the path goes through a value that is not
+a record. For a table, the value is merged at the path of every row, so a nested field can be
+patched across the whole table. Then every row has to be a record, with a record at each step of
+the path."#
     }
 
     fn signature(&self) -> nu_protocol::Signature {
@@ -26,14 +79,80 @@ repeating this process with row 1, and so on."#
             .input_output_types(vec![
                 (Type::record(), Type::record()),
                 (Type::table(), Type::table()),
+                (Type::list(Type::Any), Type::list(Type::Any)),
+                // the report of --check
+                (Type::record(), Type::table()),
             ])
             .required(
                 "value",
                 SyntaxShape::OneOf(vec![
                     SyntaxShape::Record(vec![]),
                     SyntaxShape::Table(vec![]),
+                    SyntaxShape::Closure(Some(vec![SyntaxShape::Record(vec![])])),
+                    // --at merges any value and --fold takes a list, so the parser can't narrow
+                    // this down, and other values are rejected at runtime with a type error
+                    SyntaxShape::Any,
                 ]),
-                "The new value to merge with.",
+                "The new value to merge with, or a closure computing it from each row.",
+            )
+            .named(
+                "at",
+                SyntaxShape::CellPath,
+                "Deep merge the value at this cell path of the input record, or of every row of a table",
+                None,
+            )
+            .switch(
+                "collect",
+                "Collect the values of overlapping columns into lists instead of overwriting them",
+                None,
+            )
+            .switch(
+                "keys-only",
+                "Only add the columns of the value which are missing in the input, without their values",
+                None,
+            )
+            .switch(
+                "case-insensitive",
+                "Match columns ignoring case, keeping the casing of the input",
+                Some('i'),
+            )
+            .named(
+                "only",
+                SyntaxShape::String,
+                "Only merge the columns of the value matching this glob pattern",
+                None,
+            )
+            .named(
+                "except",
+                SyntaxShape::String,
+                "Don't merge the columns of the value matching this glob pattern",
+                None,
+            )
+            .switch(
+                "strict",
+                "Error if the value has columns which are not in the input",
+                None,
+            )
+            .switch(
+                "zip",
+                "Pair up the elements of two lists of the same length",
+                None,
+            )
+            .named(
+                "default",
+                SyntaxShape::Any,
+                "The value of columns added with --keys-only (default null)",
+                None,
+            )
+            .switch(
+                "fold",
+                "Merge each record of a list into the input, in order",
+                None,
+            )
+            .switch(
+                "check",
+                "Report the changes the merge would make, without merging",
+                None,
             )
             .category(Category::Filters)
     }
@@ -78,6 +197,131 @@ repeating this process with row 1, and so on."#
                     "columnB" => Value::test_string("B0"),
                 })])),
             },
+            Example {
+                example: "{a: {}} | merge --at a.b 5",
+                description: "Set a nested value, creating missing records",
+                result: Some(Value::test_record(record! {
+                    "a" => Value::test_record(record! {
+                        "b" => Value::test_int(5),
+                    }),
+                })),
+            },
+            Example {
+                example: "[{name: a, meta: {}} {name: b, meta: {flags: {quiet: true}}}] | merge --at meta.flags {verbose: true}",
+                description: "Set a nested value in every row of a table",
+                result: Some(Value::test_list(vec![
+                    Value::test_record(record! {
+                        "name" => Value::test_string("a"),
+                        "meta" => Value::test_record(record! {
+                            "flags" => Value::test_record(record! {
+                                "verbose" => Value::test_bool(true),
+                            }),
+                        }),
+                    }),
+                    Value::test_record(record! {
+                        "name" => Value::test_string("b"),
+                        "meta" => Value::test_record(record! {
+                            "flags" => Value::test_record(record! {
+                                "quiet" => Value::test_bool(true),
+                                "verbose" => Value::test_bool(true),
+                            }),
+                        }),
+                    }),
+                ])),
+            },
+            Example {
+                example: "{a: 1, b: 2} | merge --collect {a: 3} | merge --collect {a: 4}",
+                description: "Collect the values of repeated columns into a list",
+                result: Some(Value::test_record(record! {
+                    "a" => Value::test_list(vec![
+                        Value::test_int(1),
+                        Value::test_int(3),
+                        Value::test_int(4),
+                    ]),
+                    "b" => Value::test_int(2),
+                })),
+            },
+            Example {
+                example: "{a: 1} | merge --keys-only {a: 0, b: 0}",
+                description: "Add missing columns without overwriting existing values",
+                result: Some(Value::test_record(record! {
+                    "a" => Value::test_int(1),
+                    "b" => Value::test_nothing(),
+                })),
+            },
+            Example {
+                example: "[1 2 3] | merge --zip [10 20 30]",
+                description: "Pair up the elements of two lists",
+                result: Some(Value::test_list(vec![
+                    Value::test_list(vec![Value::test_int(1), Value::test_int(10)]),
+                    Value::test_list(vec![Value::test_int(2), Value::test_int(20)]),
+                    Value::test_list(vec![Value::test_int(3), Value::test_int(30)]),
+                ])),
+            },
+            Example {
+                example: "{Path: '/bin'} | merge --case-insensitive {PATH: '/usr/bin'}",
+                description: "Merge environment variables, ignoring the case of their names",
+                result: Some(Value::test_record(record! {
+                    "Path" => Value::test_string("/usr/bin"),
+                })),
+            },
+            Example {
+                example: "{config_color: red, name: a} | merge --only 'config_*' {config_color: blue, config_size: 2, name: b}",
+                description: "Merge only the columns matching a pattern",
+                result: Some(Value::test_record(record! {
+                    "config_color" => Value::test_string("blue"),
+                    "name" => Value::test_string("a"),
+                    "config_size" => Value::test_int(2),
+                })),
+            },
+            Example {
+                example: "{a: 1, b: 2, c: 3} | merge --check {a: 1, b: 5, d: 4}",
+                description: "Check which columns a merge would change",
+                result: Some(Value::test_list(vec![
+                    Value::test_record(record! {
+                        "column" => Value::test_string("a"),
+                        "change" => Value::test_string("unchanged"),
+                    }),
+                    Value::test_record(record! {
+                        "column" => Value::test_string("b"),
+                        "change" => Value::test_string("overwritten"),
+                    }),
+                    Value::test_record(record! {
+                        "column" => Value::test_string("d"),
+                        "change" => Value::test_string("added"),
+                    }),
+                ])),
+            },
+            Example {
+                example: "[{a: 1} {a: 2}] | merge {|row| {b: ($row.a * 10)} }",
+                description: "Merge a record computed from each row",
+                result: Some(Value::test_list(vec![
+                    Value::test_record(record! {
+                        "a" => Value::test_int(1),
+                        "b" => Value::test_int(10),
+                    }),
+                    Value::test_record(record! {
+                        "a" => Value::test_int(2),
+                        "b" => Value::test_int(20),
+                    }),
+                ])),
+            },
+            Example {
+                example: "{a: 1, b: 1} | merge --fold [{a: 2} {a: 3, c: 3}]",
+                description: "Merge several records in order, with later ones winning",
+                result: Some(Value::test_record(record! {
+                    "a" => Value::test_int(3),
+                    "b" => Value::test_int(1),
+                    "c" => Value::test_int(3),
+                })),
+            },
+            Example {
+                example: "{a: 1} | merge null",
+                description: "Merging null leaves the input unchanged",
+                result: Some(Value::test_record(record! {
+                    "a" => Value::test_int(1),
+                })),
+            },
         ]
     }
 
@@ -90,19 +334,308 @@ repeating this process with row 1, and so on."#
     ) -> Result<PipelineData, ShellError> {
         let head = call.head;
         let merge_value: Value = call.req(engine_state, stack, 0)?;
+        let at: Option<CellPath> = call.get_flag(engine_state, stack, "at")?;
+        let collect = call.has_flag(engine_state, stack, "collect")?;
+        let keys_only = call.has_flag(engine_state, stack, "keys-only")?;
+        let zip = call.has_flag(engine_state, stack, "zip")?;
+        let strict = call.has_flag(engine_state, stack, "strict")?;
+        let case_insensitive = call.has_flag(engine_state, stack, "case-insensitive")?;
+        let default: Option<Value> = call.get_flag(engine_state, stack, "default")?;
+        let check = call.has_flag(engine_state, stack, "check")?;
+        let fold = call.has_flag(engine_state, stack, "fold")?;
+        let only = glob_flag(engine_state, stack, call, "only")?;
+        let except = glob_flag(engine_state, stack, call, "except")?;
         let metadata = input.metadata();
 
+        // these flags each select a different kind of merge
+        let modes: Vec<&str> = [
+            ("at", at.is_some()),
+            ("collect", collect),
+            ("keys-only", keys_only),
+            ("zip", zip),
+        ]
+        .into_iter()
+        .filter_map(|(flag, set)| set.then_some(flag))
+        .collect();
+        if let [first, second, ..] = modes[..] {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: format!("`--{second}` cannot be used with `--{first}`"),
+                span: call.get_flag_span(stack, second).expect("has flag"),
+            });
+        }
+        if strict && let Some(mode) = modes.iter().find(|mode| **mode != "collect") {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: format!("`--strict` cannot be used with `--{mode}`"),
+                span: call.get_flag_span(stack, "strict").expect("has flag"),
+            });
+        }
+        if check && let Some(mode) = modes.iter().find(|mode| ["at", "zip"].contains(mode)) {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: format!("`--check` cannot be used with `--{mode}`"),
+                span: call.get_flag_span(stack, "check").expect("has flag"),
+            });
+        }
+        // these flags work on a single record or table
+        let single_merge_flag = [("at", at.is_some()), ("zip", zip), ("check", check)]
+            .into_iter()
+            .find_map(|(flag, set)| set.then_some(flag));
+        if let Some(flag) = single_merge_flag {
+            if fold {
+                return Err(ShellError::IncompatibleParametersSingle {
+                    msg: format!("`--{flag}` cannot be used with `--fold`"),
+                    span: call.get_flag_span(stack, flag).expect("has flag"),
+                });
+            }
+            if matches!(merge_value, Value::Closure { .. }) {
+                return Err(ShellError::IncompatibleParametersSingle {
+                    msg: format!("`--{flag}` cannot be used with a closure"),
+                    span: call.get_flag_span(stack, flag).expect("has flag"),
+                });
+            }
+        }
+        if let Some(flag) = [("only", only.is_some()), ("except", except.is_some())]
+            .into_iter()
+            .find_map(|(flag, set)| set.then_some(flag))
+            && let Some(mode) = modes.iter().find(|mode| ["at", "zip"].contains(mode))
+        {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: format!("`--{flag}` cannot be used with `--{mode}`"),
+                span: call.get_flag_span(stack, flag).expect("has flag"),
+            });
+        }
+        if default.is_some() && !keys_only {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--default` can only be used with `--keys-only`".into(),
+                span: call.get_flag_span(stack, "default").expect("has flag"),
+            });
+        }
+
         // collect input before typechecking, so tables are detected as such
         let input_span = input.span().unwrap_or(head);
         let input = input.into_value(input_span)?;
 
+        if let Some(at) = at {
+            let signals = engine_state.signals();
+            let merged = match input {
+                // an empty path merges into the table itself
+                Value::List { vals, .. } if !at.members.is_empty() => Value::list(
+                    vals.into_iter()
+                        .map(|row| merge_at(row, &at.members, merge_value.clone(), signals, head))
+                        .collect::<Result<_, _>>()?,
+                    input_span,
+                ),
+                input => merge_at(input, &at.members, merge_value, signals, head)?,
+            };
+            return Ok(merged.into_pipeline_data_with_metadata(metadata));
+        }
+
+        if merge_value.is_nothing() {
+            if check {
+                return Ok(Value::list(vec![], head).into_pipeline_data());
+            }
+            return Ok(input.into_pipeline_data_with_metadata(metadata));
+        }
+
+        let match_options = MatchOptions {
+            case_sensitive: !case_insensitive,
+            ..MatchOptions::default()
+        };
+        let is_selected = |col: &str| {
+            only.as_ref()
+                .is_none_or(|only| only.matches_with(col, match_options))
+                && except
+                    .as_ref()
+                    .is_none_or(|except| !except.matches_with(col, match_options))
+        };
+        let select_columns = |merge_value: Value| {
+            if only.is_some() || except.is_some() {
+                retain_columns(merge_value, &is_selected)
+            } else {
+                merge_value
+            }
+        };
+
+        // a single merge of the values computed by a closure or folded from a list
+        let merge_row = |row: Value, merge_value: Value| {
+            if merge_value.is_nothing() {
+                return Ok(row);
+            }
+            typecheck_merge(&row, &merge_value, head)?;
+            let merge_value = select_columns(merge_value);
+            if strict {
+                check_strict(&row, &merge_value, case_insensitive, head)?;
+            }
+            let (merge_value, strategy) =
+                merge_strategy(merge_value, collect, keys_only, default.as_ref(), head);
+            do_merge(
+                row,
+                merge_value,
+                strategy,
+                case_insensitive,
+                engine_state.signals(),
+                head,
+            )
+        };
+
+        if fold {
+            let merged = fold_merge(input, merge_value, merge_row)?;
+            return Ok(merged.into_pipeline_data_with_metadata(metadata));
+        }
+
+        if let Value::Closure { val, .. } = merge_value {
+            let merged = match input {
+                Value::List { vals, .. } => {
+                    let mut closure = ClosureEval::new(engine_state, stack, *val);
+                    let vals = vals
+                        .into_iter()
+                        .map(|row| {
+                            let merge_value =
+                                closure_result(closure.run_with_value(row.clone())?, head)?;
+                            merge_row(row, merge_value)
+                        })
+                        .collect::<Result<_, ShellError>>()?;
+                    Value::list(vals, input_span)
+                }
+                input => {
+                    let closure = ClosureEvalOnce::new(engine_state, stack, *val);
+                    let merge_value = closure_result(closure.run_with_value(input.clone())?, head)?;
+                    merge_row(input, merge_value)?
+                }
+            };
+            return Ok(merged.into_pipeline_data_with_metadata(metadata));
+        }
+
+        if zip {
+            let zipped = zip_lists(input, merge_value, engine_state.signals(), head)?;
+            return Ok(zipped.into_pipeline_data_with_metadata(metadata));
+        }
+
         typecheck_merge(&input, &merge_value, head)?;
+        let merge_value = select_columns(merge_value);
 
-        let merged = do_merge(input, merge_value, MergeStrategy::Shallow, head)?;
+        if strict {
+            check_strict(&input, &merge_value, case_insensitive, head)?;
+        }
+
+        let (merge_value, strategy) =
+            merge_strategy(merge_value, collect, keys_only, default.as_ref(), head);
+
+        if check {
+            let report = check_merge(
+                &input,
+                &merge_value,
+                strategy,
+                case_insensitive,
+                engine_state.signals(),
+                head,
+            )?;
+            return Ok(report.into_pipeline_data());
+        }
+
+        let merged = do_merge(
+            input,
+            merge_value,
+            strategy,
+            case_insensitive,
+            engine_state.signals(),
+            head,
+        )?;
         Ok(merged.into_pipeline_data_with_metadata(metadata))
     }
 }
 
+/// The glob pattern of the flag `name`, if it is given
+fn glob_flag(
+    engine_state: &EngineState,
+    stack: &mut Stack,
+    call: &Call,
+    name: &str,
+) -> Result<Option<Pattern>, ShellError> {
+    let Some(pattern) = call.get_flag::<Spanned<String>>(engine_state, stack, name)? else {
+        return Ok(None);
+    };
+    Pattern::new(&pattern.item)
+        .map(Some)
+        .map_err(|err| ShellError::InvalidGlobPattern {
+            msg: err.msg.into(),
+            span: pattern.span,
+        })
+}
+
+/// The strategy selected by the flags, with the value prepared for it
+fn merge_strategy(
+    merge_value: Value,
+    collect: bool,
+    keys_only: bool,
+    default: Option<&Value>,
+    head: Span,
+) -> (Value, MergeStrategy) {
+    if keys_only {
+        let default = default.cloned().unwrap_or(Value::nothing(head));
+        (
+            replace_values(merge_value, &default),
+            MergeStrategy::KeysOnly,
+        )
+    } else if collect {
+        (merge_value, MergeStrategy::Collect)
+    } else {
+        (merge_value, MergeStrategy::Shallow)
+    }
+}
+
+/// Error if `merge_value` has columns which are not in `input`, for `--strict`
+fn check_strict(
+    input: &Value,
+    merge_value: &Value,
+    case_insensitive: bool,
+    head: Span,
+) -> Result<(), ShellError> {
+    let normalize = |col: String| {
+        if case_insensitive {
+            col.to_lowercase()
+        } else {
+            col
+        }
+    };
+    let columns: Vec<String> = columns_of(input).into_iter().map(normalize).collect();
+    let unexpected: Vec<String> = columns_of(merge_value)
+        .into_iter()
+        .filter(|col| !columns.contains(&normalize(col.clone())))
+        .collect();
+    if !unexpected.is_empty() {
+        return Err(ShellError::IncorrectValue {
+            msg: format!(
+                "unexpected columns not in the input: {}",
+                unexpected.join(", ")
+            ),
+            val_span: merge_value.span(),
+            call_span: head,
+        });
+    }
+    Ok(())
+}
+
+/// The record returned by the closure argument, which may also be null to merge nothing
+fn closure_result(result: PipelineData, head: Span) -> Result<Value, ShellError> {
+    let value = result.into_value(head)?;
+    match value {
+        Value::Record { .. } | Value::Nothing { .. } => Ok(value),
+        Value::Error { error, .. } => Err(*error),
+        other => Err(ShellError::TypeMismatch {
+            err_message: format!("the closure must return a record, not {}", other.get_type()),
+            span: other.span(),
+        }),
+    }
+}
+
+/// The columns of a record, or of all the records in a list
+fn columns_of(value: &Value) -> Vec<String> {
+    match value {
+        Value::List { vals, .. } => get_columns(vals),
+        other => get_columns(std::slice::from_ref(other)),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
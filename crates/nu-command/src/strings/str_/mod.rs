@@ -12,6 +12,8 @@ mod starts_with;
 mod stats;
 mod substring;
 mod trim;
+mod visible_slice;
+mod wrap_ansi;
 
 pub use case::*;
 pub use contains::StrContains;
@@ -27,3 +29,5 @@ pub use starts_with::StrStartsWith;
 pub use stats::StrStats;
 pub use substring::StrSubstring;
 pub use trim::StrTrim;
+pub use visible_slice::StrVisibleSlice;
+pub use wrap_ansi::StrWrapAnsi;
@@ -0,0 +1,188 @@
+use std::ops::Bound;
+
+use ansi_str::AnsiStr;
+use nu_cmd_base::input_handler::{CmdArgument, operate};
+use nu_engine::command_prelude::*;
+use nu_protocol::IntRange;
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Clone)]
+pub struct StrVisibleSlice;
+
+struct Arguments {
+    range: IntRange,
+    cell_paths: Option<Vec<CellPath>>,
+}
+
+impl CmdArgument for Arguments {
+    fn take_cell_paths(&mut self) -> Option<Vec<CellPath>> {
+        self.cell_paths.take()
+    }
+}
+
+impl Command for StrVisibleSlice {
+    fn name(&self) -> &str {
+        "str visible-slice"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("str visible-slice")
+            .input_output_types(vec![
+                (Type::String, Type::String),
+                (
+                    Type::List(Box::new(Type::String)),
+                    Type::List(Box::new(Type::String)),
+                ),
+                (Type::table(), Type::table()),
+                (Type::record(), Type::record()),
+            ])
+            .allow_variants_without_examples(true)
+            .required(
+                "range",
+                SyntaxShape::Range,
+                "The indexes of the visible characters to keep.",
+            )
+            .rest(
+                "rest",
+                SyntaxShape::CellPath,
+                "For a data structure input, slice strings at the given cell paths.",
+            )
+            .category(Category::Strings)
+    }
+
+    fn description(&self) -> &str {
+        "Get part of a string by its visible characters, keeping its ANSI styling intact."
+    }
+
+    fn extra_description(&self) -> &str {
+        r#"Unlike `str substring`, ANSI escape sequences are not counted, and they are never cut in
+half. Indexes count grapheme clusters, so every visible character has length 1. The styles which
+are active at the start of the slice are applied again, and the slice ends with a reset of the
+styles that are still active."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["substring", "ansi", "color", "truncate"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let range: IntRange = call.req(engine_state, stack, 0)?;
+
+        let cell_paths: Vec<CellPath> = call.rest(engine_state, stack, 1)?;
+        let cell_paths = (!cell_paths.is_empty()).then_some(cell_paths);
+        let args = Arguments { range, cell_paths };
+        operate(action, args, input, call.head, engine_state.signals()).map(|pipeline| {
+            // a slice of text/json is not necessarily text/json itself
+            let metadata = pipeline.metadata().map(|m| m.with_content_type(None));
+            pipeline.set_metadata(metadata)
+        })
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Get the first five visible characters of a colored string",
+                example: "$'(ansi red)good(ansi reset) nushell' | str visible-slice 0..4",
+                result: None,
+            },
+            Example {
+                description: "Slice a string without styling, like `str substring --grapheme-clusters`",
+                example: "'good nushell' | str visible-slice 5..",
+                result: Some(Value::test_string("nushell")),
+            },
+        ]
+    }
+}
+
+fn action(input: &Value, args: &Arguments, head: Span) -> Value {
+    match input {
+        Value::String { val: s, .. } => {
+            let visible = s.ansi_strip();
+            let indices = visible
+                .grapheme_indices(true)
+                .map(|(idx, s)| (idx, s.len()))
+                .collect::<Vec<_>>();
+
+            let (idx_start, idx_end) = args.range.absolute_bounds(indices.len());
+            let idx_range = match idx_end {
+                Bound::Excluded(end) => &indices[idx_start..end],
+                Bound::Included(end) => &indices[idx_start..=end],
+                Bound::Unbounded => &indices[idx_start..],
+            };
+
+            // `ansi_cut` takes byte offsets into the visible string, and panics if they are not on
+            // char boundaries, which grapheme boundaries always are
+            let sliced = match idx_range.first().zip(idx_range.last()) {
+                Some((start, end)) => s.ansi_cut(start.0..end.0 + end.1).into_owned(),
+                None => String::new(),
+            };
+            Value::string(sliced, head)
+        }
+        // Propagate errors by explicitly matching them before the final case.
+        Value::Error { .. } => input.clone(),
+        other => Value::error(
+            ShellError::UnsupportedInput {
+                msg: "Only string values are supported".into(),
+                input: format!("input type: {:?}", other.get_type()),
+                msg_span: head,
+                input_span: other.span(),
+            },
+            head,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_examples() {
+        use crate::test_examples;
+
+        test_examples(StrVisibleSlice {})
+    }
+
+    fn slice(input: &str, start: i64, end: i64) -> Value {
+        let range = IntRange::new(
+            Value::test_int(start),
+            Value::test_int(start + 1),
+            Value::test_int(end),
+            nu_protocol::ast::RangeInclusion::RightExclusive,
+            Span::test_data(),
+        )
+        .expect("valid range");
+        let args = Arguments {
+            range,
+            cell_paths: None,
+        };
+        action(&Value::test_string(input), &args, Span::test_data())
+    }
+
+    #[test]
+    fn keeps_style_at_cut_points() {
+        let actual = slice("\u{1b}[31mhello\u{1b}[0m world", 1, 3);
+        assert_eq!(actual, Value::test_string("\u{1b}[31mel\u{1b}[39m"));
+    }
+
+    #[test]
+    fn does_not_count_escape_sequences() {
+        let actual = slice("\u{1b}[1mab\u{1b}[0mcd", 1, 3);
+        assert_eq!(
+            actual.as_str().map(AnsiStr::ansi_strip).ok(),
+            Some("bc".into())
+        );
+    }
+
+    #[test]
+    fn counts_graphemes() {
+        let actual = slice("🇯🇵ほげ", 1, 2);
+        assert_eq!(actual, Value::test_string("ほ"));
+    }
+}
@@ -0,0 +1,292 @@
+use ansi_str::AnsiStr;
+use nu_cmd_base::input_handler::{CmdArgument, operate};
+use nu_engine::command_prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Clone)]
+pub struct StrWrapAnsi;
+
+struct Arguments {
+    width: usize,
+    preserve_indent: bool,
+    cell_paths: Option<Vec<CellPath>>,
+}
+
+impl CmdArgument for Arguments {
+    fn take_cell_paths(&mut self) -> Option<Vec<CellPath>> {
+        self.cell_paths.take()
+    }
+}
+
+impl Command for StrWrapAnsi {
+    fn name(&self) -> &str {
+        "str wrap-ansi"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("str wrap-ansi")
+            .input_output_types(vec![
+                (Type::String, Type::String),
+                (
+                    Type::List(Box::new(Type::String)),
+                    Type::List(Box::new(Type::String)),
+                ),
+                (Type::table(), Type::table()),
+                (Type::record(), Type::record()),
+            ])
+            .allow_variants_without_examples(true)
+            .required(
+                "width",
+                SyntaxShape::Int,
+                "The maximum visible width of a line.",
+            )
+            .switch(
+                "preserve-indent",
+                "Indent the wrapped lines like the line they were wrapped from.",
+                Some('i'),
+            )
+            .rest(
+                "rest",
+                SyntaxShape::CellPath,
+                "For a data structure input, wrap strings at the given cell paths.",
+            )
+            .category(Category::Strings)
+    }
+
+    fn description(&self) -> &str {
+        "Wrap a string to a maximum visible width, keeping its ANSI styling intact."
+    }
+
+    fn extra_description(&self) -> &str {
+        r#"Lines are broken at the last whitespace that fits, or in the middle of a word which is
+longer than the width. ANSI escape sequences don't count towards the width and are never cut in
+half. Every wrapped line starts with the styles which are active at its start, and ends with a
+reset of the styles that are still active, so each line can be printed on its own."#
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        vec!["color", "width", "fold", "paragraph", "line break"]
+    }
+
+    fn run(
+        &self,
+        engine_state: &EngineState,
+        stack: &mut Stack,
+        call: &Call,
+        input: PipelineData,
+    ) -> Result<PipelineData, ShellError> {
+        let width: Spanned<i64> = call.req(engine_state, stack, 0)?;
+        let width = match usize::try_from(width.item) {
+            Ok(width) if width > 0 => width,
+            _ => return Err(ShellError::NeedsPositiveValue { span: width.span }),
+        };
+        let preserve_indent = call.has_flag(engine_state, stack, "preserve-indent")?;
+
+        let cell_paths: Vec<CellPath> = call.rest(engine_state, stack, 1)?;
+        let cell_paths = (!cell_paths.is_empty()).then_some(cell_paths);
+        let args = Arguments {
+            width,
+            preserve_indent,
+            cell_paths,
+        };
+        operate(action, args, input, call.head, engine_state.signals())
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                description: "Wrap a colored paragraph to 20 columns",
+                example: "$'(ansi green)Nushell is a new type of shell(ansi reset)' | str wrap-ansi 20",
+                result: None,
+            },
+            Example {
+                description: "Wrap a string without styling",
+                example: "'good nushell' | str wrap-ansi 5",
+                result: Some(Value::test_string("good\nnushe\nll")),
+            },
+            Example {
+                description: "Keep the indentation of wrapped lines",
+                example: "'  one two three' | str wrap-ansi --preserve-indent 9",
+                result: Some(Value::test_string("  one two\n  three")),
+            },
+        ]
+    }
+}
+
+fn action(input: &Value, args: &Arguments, head: Span) -> Value {
+    match input {
+        Value::String { val: s, .. } => {
+            Value::string(wrap(s, args.width, args.preserve_indent), head)
+        }
+        // Propagate errors by explicitly matching them before the final case.
+        Value::Error { .. } => input.clone(),
+        other => Value::error(
+            ShellError::UnsupportedInput {
+                msg: "Only string values are supported".into(),
+                input: format!("input type: {:?}", other.get_type()),
+                msg_span: head,
+                input_span: other.span(),
+            },
+            head,
+        ),
+    }
+}
+
+fn is_whitespace(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
+}
+
+/// Wrap every line of `text`, carrying the styles which are still active over its line breaks
+fn wrap(text: &str, width: usize, preserve_indent: bool) -> String {
+    if !text.contains('\n') {
+        return wrap_line(text, width, preserve_indent).join("\n");
+    }
+
+    // cutting each line out of the whole text starts it with the styles active at its start
+    let visible = text.ansi_strip();
+    let mut start = 0;
+    visible
+        .split('\n')
+        .flat_map(|line| {
+            let range = start..start + line.len();
+            start = range.end + 1;
+            if line.is_empty() {
+                // no need to style an empty line
+                return vec![String::new()];
+            }
+            wrap_line(&text.ansi_cut(range), width, preserve_indent)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split a single line into lines of at most `width` visible columns.
+fn wrap_line(line: &str, width: usize, preserve_indent: bool) -> Vec<String> {
+    let visible = line.ansi_strip();
+    if visible.width() <= width {
+        return vec![line.to_string()];
+    }
+
+    let graphemes = visible.grapheme_indices(true).collect::<Vec<_>>();
+    let indent = if preserve_indent {
+        let len = graphemes
+            .iter()
+            .take_while(|(_, g)| is_whitespace(g))
+            .map(|(_, g)| g.len())
+            .sum();
+        Some(&visible[..len]).filter(|indent| indent.width() < width)
+    } else {
+        None
+    };
+
+    let mut lines = Vec::new();
+    let mut i = 0;
+    while i < graphemes.len() {
+        let (prefix, limit) = match indent {
+            Some(indent) if !lines.is_empty() => (indent, width - indent.width()),
+            _ => ("", width),
+        };
+
+        let start = graphemes[i].0;
+        let mut line_width = 0;
+        let mut last_space = None;
+        let mut seen_word = false;
+        let mut end = i;
+        while end < graphemes.len() {
+            let grapheme = graphemes[end].1;
+            let grapheme_width = grapheme.width();
+            // a grapheme wider than the line still has to go somewhere
+            if line_width + grapheme_width > limit && end > i {
+                break;
+            }
+            if !is_whitespace(grapheme) {
+                seen_word = true;
+            } else if seen_word {
+                last_space = Some(end);
+            }
+            line_width += grapheme_width;
+            end += 1;
+        }
+
+        let mut next = end;
+        if end < graphemes.len()
+            && !is_whitespace(graphemes[end].1)
+            && let Some(space) = last_space
+        {
+            end = space;
+            next = space;
+        }
+
+        // an indentation which doesn't fit on the line is dropped
+        if seen_word {
+            let end_byte = graphemes.get(end).map_or(visible.len(), |(idx, _)| *idx);
+            let end_byte = start + visible[start..end_byte].trim_end().len();
+            let cut = line.ansi_cut(start..end_byte);
+            lines.push(format!("{prefix}{cut}"));
+        }
+
+        // the whitespace at a break is replaced by the line break
+        i = next;
+        while i < graphemes.len() && is_whitespace(graphemes[i].1) {
+            i += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_examples() {
+        use crate::test_examples;
+
+        test_examples(StrWrapAnsi {})
+    }
+
+    #[test]
+    fn reapplies_style_on_each_line() {
+        let actual = wrap_line("\u{1b}[31mab cd\u{1b}[0m", 2, false);
+        assert_eq!(
+            actual,
+            vec!["\u{1b}[31mab\u{1b}[39m", "\u{1b}[31mcd\u{1b}[0m"]
+        );
+    }
+
+    #[test]
+    fn reapplies_style_after_line_break() {
+        let actual = wrap("\u{1b}[32maaa bbb\nccc ddd\u{1b}[0m", 3, false);
+        assert_eq!(
+            actual,
+            "\u{1b}[32maaa\u{1b}[39m\n\u{1b}[32mbbb\u{1b}[39m\n\u{1b}[32mccc\u{1b}[39m\n\u{1b}[32mddd\u{1b}[0m"
+        );
+    }
+
+    #[test]
+    fn does_not_count_escape_sequences() {
+        let line = "\u{1b}[1mbold\u{1b}[0m text";
+        assert_eq!(wrap_line(line, 9, false), vec![line]);
+    }
+
+    #[test]
+    fn breaks_long_words() {
+        assert_eq!(wrap_line("abcdefg", 3, false), vec!["abc", "def", "g"]);
+    }
+
+    #[test]
+    fn counts_wide_characters() {
+        assert_eq!(wrap_line("ほげほげ", 4, false), vec!["ほげ", "ほげ"]);
+    }
+
+    #[test]
+    fn keeps_characters_wider_than_the_line() {
+        assert_eq!(wrap_line("ほげ", 1, false), vec!["ほ", "げ"]);
+    }
+
+    #[test]
+    fn drops_indent_wider_than_the_line() {
+        assert_eq!(wrap_line("    ab", 3, true), vec!["ab"]);
+    }
+}
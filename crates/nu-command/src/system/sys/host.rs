@@ -14,6 +14,11 @@ impl Command for SysHost {
     fn signature(&self) -> Signature {
         Signature::build("sys host")
             .filter()
+            .switch(
+                "strict",
+                "Return an error if the operating system does not provide a hostname",
+                None,
+            )
             .category(Category::System)
             .input_output_types(vec![(Type::Nothing, Type::record())])
     }
@@ -22,26 +27,38 @@ impl Command for SysHost {
         "View information about the system host."
     }
 
+    fn extra_description(&self) -> &str {
+        "The hostname is null if the operating system does not provide one. Use `--strict` to get an error instead."
+    }
+
     fn run(
         &self,
-        _engine_state: &EngineState,
-        _stack: &mut Stack,
+        engine_state: &EngineState,
+        stack: &mut Stack,
         call: &Call,
         _input: PipelineData,
     ) -> Result<PipelineData, ShellError> {
-        Ok(host(call.head).into_pipeline_data())
+        let strict = call.has_flag(engine_state, stack, "strict")?;
+        Ok(host(strict, call.head)?.into_pipeline_data())
     }
 
     fn examples(&self) -> Vec<Example<'_>> {
-        vec![Example {
-            description: "Show info about the system host",
-            example: "sys host",
-            result: None,
-        }]
+        vec![
+            Example {
+                description: "Show info about the system host",
+                example: "sys host",
+                result: None,
+            },
+            Example {
+                description: "Get the hostname, failing if it is not available",
+                example: "(sys host --strict).hostname",
+                result: None,
+            },
+        ]
     }
 }
 
-fn host(span: Span) -> Value {
+fn host(strict: bool, span: Span) -> Result<Value, ShellError> {
     let mut record = Record::new();
 
     if let Some(name) = System::name() {
@@ -62,9 +79,20 @@ fn host(span: Span) -> Value {
             Value::string(trim_cstyle_null(version), span),
         );
     }
-    if let Some(hostname) = System::host_name() {
-        record.push("hostname", Value::string(trim_cstyle_null(hostname), span));
-    }
+    let hostname = match hostname(System::host_name()) {
+        Some(hostname) => Value::string(hostname, span),
+        None if strict => {
+            return Err(ShellError::GenericError {
+                error: "Hostname not available".into(),
+                msg: "the operating system did not provide a hostname".into(),
+                span: Some(span),
+                help: Some("remove `--strict` to get null instead".into()),
+                inner: vec![],
+            });
+        }
+        None => Value::nothing(span),
+    };
+    record.push("hostname", hostname);
 
     let uptime = System::uptime()
         .saturating_mul(1_000_000_000)
@@ -79,7 +107,14 @@ fn host(span: Span) -> Value {
 
     record.push("boot_time", boot_time);
 
-    Value::record(record, span)
+    Ok(Value::record(record, span))
+}
+
+/// Some systems (e.g. containers) report an empty hostname, which is treated like a missing one.
+fn hostname(hostname: Option<String>) -> Option<String> {
+    hostname
+        .map(trim_cstyle_null)
+        .filter(|hostname| !hostname.trim().is_empty())
 }
 
 fn boot_time() -> Option<DateTime<FixedOffset>> {
@@ -90,3 +125,16 @@ fn boot_time() -> Option<DateTime<FixedOffset>> {
     let time = DateTime::from_timestamp(secs, 0)?;
     Some(time.with_timezone(&Local).fixed_offset())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_hostname_is_nothing() {
+        assert_eq!(hostname(Some("".into())), None);
+        assert_eq!(hostname(Some(" \0".into())), None);
+        assert_eq!(hostname(None), None);
+        assert_eq!(hostname(Some("nu\0".into())), Some("nu".into()));
+    }
+}
@@ -57,7 +57,7 @@ impl Command for Table {
                     .short('t')
                     .arg(SyntaxShape::String)
                     .desc("set a table mode/theme")
-                    .completion(Completion::new_list(SUPPORTED_TABLE_MODES)),
+                    .completion(Completion::new_list(TableMode::NAMES)),
             )
             .named(
                 "index",
@@ -1167,30 +1167,8 @@ fn convert_table_to_output(
     }
 }
 
-const SUPPORTED_TABLE_MODES: &[&str] = &[
-    "basic",
-    "compact",
-    "compact_double",
-    "default",
-    "heavy",
-    "light",
-    "none",
-    "reinforced",
-    "rounded",
-    "thin",
-    "with_love",
-    "psql",
-    "markdown",
-    "dots",
-    "restructured",
-    "ascii_rounded",
-    "basic_compact",
-    "single",
-    "double",
-];
-
 fn supported_table_modes() -> Vec<Value> {
-    SUPPORTED_TABLE_MODES
+    TableMode::NAMES
         .iter()
         .copied()
         .map(Value::test_string)
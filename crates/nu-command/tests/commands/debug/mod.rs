@@ -1,2 +1,7 @@
 mod metadata_set;
+mod profile;
+mod status;
 mod timeit;
+mod trace;
+mod view_ir;
+mod watchdog;
@@ -0,0 +1,276 @@
+use nu_test_support::fs::Stub::FileWithContent;
+use nu_test_support::nu;
+use nu_test_support::playground::Playground;
+
+// `nu!` joins the lines of its command, so multiline source has to come from a file
+const MULTILINE_DEF: &str = "def multiline [] { do {
+    'spam'
+} }";
+
+#[test]
+fn profile_truncates_multiline_source() {
+    Playground::setup("profile_truncates_multiline_source", |dirs, sandbox| {
+        sandbox.with_files(&[FileWithContent("multiline.nu", MULTILINE_DEF)]);
+
+        let actual = nu!(
+            cwd: dirs.test(),
+            "source multiline.nu; debug profile { multiline } | get source | any { str ends-with ' ...' }"
+        );
+        assert_eq!(actual.out, "true");
+    })
+}
+
+#[test]
+fn profile_expand_source_keeps_full_source() {
+    Playground::setup(
+        "profile_expand_source_keeps_full_source",
+        |dirs, sandbox| {
+            sandbox.with_files(&[FileWithContent("multiline.nu", MULTILINE_DEF)]);
+
+            let actual = nu!(
+                cwd: dirs.test(),
+                "source multiline.nu; debug profile --expand-source { multiline } | get source | any { str contains (char nl) }"
+            );
+            assert_eq!(actual.out, "true");
+        },
+    )
+}
+
+const RECURSIVE_DEF: &str = "def f [n: int] { if $n > 0 { f ($n - 1) } }";
+
+#[test]
+fn profile_collapse_recursion_reduces_rows() {
+    let actual = nu!(format!(
+        "{RECURSIVE_DEF}
+        let full = debug profile --max-depth 100 {{ f 10 }} | length
+        let collapsed = debug profile --max-depth 100 --collapse-recursion {{ f 10 }} | length
+        $collapsed < $full"
+    ));
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_collapse_recursion_counts_calls() {
+    let actual = nu!(format!(
+        "{RECURSIVE_DEF}; debug profile --max-depth 100 --collapse-recursion {{ f 10 }} | get count | math max"
+    ));
+    assert_eq!(actual.out, "10");
+}
+
+#[test]
+fn profile_max_depth_skips_deeper_instructions() {
+    let actual = nu!("debug profile --max-depth 1 { do { do { 'spam' } } } | get depth | math max");
+    assert_eq!(actual.out, "1");
+}
+
+#[test]
+fn profile_output_file_by_extension() {
+    Playground::setup("profile_output_file_by_extension", |dirs, _| {
+        let actual = nu!(
+            cwd: dirs.test(),
+            "debug profile { 'spam' } --output-file profile.json | describe"
+        );
+        assert_eq!(actual.out, "nothing");
+
+        let actual = nu!(
+            cwd: dirs.test(),
+            "debug profile { 'spam' } -o profile.csv;
+            [(open profile.json | get source) (open profile.csv | get pc)] | to nuon"
+        );
+        assert_eq!(
+            actual.out,
+            r#"[["debug profile { 'spam' } --output-file profile.json", "'spam'", "{ 'spam' }"], [0, 0, 1]]"#
+        );
+    })
+}
+
+#[test]
+fn profile_output_file_unsupported_extension() {
+    Playground::setup("profile_output_file_unsupported_extension", |dirs, _| {
+        let actual = nu!(cwd: dirs.test(), "debug profile { 'spam' } --output-file profile.txt");
+        assert!(actual.err.contains("unsupported profile format"));
+        assert!(!dirs.test().join("profile.txt").exists());
+    })
+}
+
+#[test]
+fn profile_granularity_call() {
+    let actual = nu!(
+        "def f [] { [1 2] | length }; debug profile --granularity call { f } | get instruction | skip 1 | all { str starts-with 'call' }"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_granularity_block() {
+    let actual =
+        nu!("debug profile --granularity block { do { 'spam' } } | select depth source | to nuon");
+    assert_eq!(
+        actual.out,
+        r#"[[depth, source]; [0, "debug profile --granularity block { do { 'spam' } }"], [1, "{ do { 'spam' } }"], [2, "{ 'spam' }"]]"#
+    );
+}
+
+#[test]
+fn profile_granularity_invalid() {
+    let actual = nu!("debug profile --granularity expr { 'spam' }");
+    assert!(actual.err.contains("instruction, block, call"));
+}
+
+#[test]
+fn profile_sample_records_fewer_instructions() {
+    let actual = nu!(
+        "let all = debug profile { 1..100 | each { $in * 2 } } | length;
+        let sampled = debug profile --sample 10 { 1..100 | each { $in * 2 } } | length;
+        $sampled < $all and $sampled > 1"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_sample_must_be_positive() {
+    let actual = nu!("debug profile --sample 0 { 'spam' }");
+    assert!(actual.err.contains("a positive int"));
+}
+
+#[test]
+fn profile_compare_matches_rows_by_source() {
+    Playground::setup("profile_compare_matches_rows_by_source", |dirs, _| {
+        let actual = nu!(
+            cwd: dirs.test(),
+            "debug profile { 'spam' } --output-file base.csv;
+            debug profile { 'spam'; 'eggs' } --compare base.csv | each {|row| [$row.source ($row.delta_ms != null)] } | to nuon"
+        );
+        assert_eq!(
+            actual.out,
+            r#"[[<start>, true], ["'spam'", true], ["{ 'spam'; 'eggs' }", false], ["'eggs'", false], ["{ 'spam'; 'eggs' }", false], ["{ 'spam' }", false]]"#
+        );
+    })
+}
+
+#[test]
+fn profile_compare_with_output_file_errors() {
+    Playground::setup("profile_compare_with_output_file_errors", |dirs, _| {
+        let actual = nu!(
+            cwd: dirs.test(),
+            "debug profile { 'spam' } -o base.json; debug profile { 'spam' } --compare base.json -o new.json"
+        );
+        assert!(actual.err.contains("cannot be used with `--compare`"));
+    })
+}
+
+#[test]
+fn profile_by_command_counts_calls() {
+    let actual = nu!(format!(
+        "{RECURSIVE_DEF}; debug profile --max-depth 100 --by command {{ f 10 }} | where command == f | get calls.0"
+    ));
+    assert_eq!(actual.out, "11");
+}
+
+#[test]
+fn profile_by_command_sorts_by_total() {
+    let actual = nu!(
+        "def slow [] { sleep 50ms }; debug profile --by command { slow; 'spam' | str length } | $in.0.command in [slow sleep] and ($in.total_ms | $in == ($in | sort --reverse))"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_by_command_self_excludes_nested_calls() {
+    let actual = nu!(
+        "def slow [] { sleep 50ms }; debug profile --by command --duration-values { slow } | where command == slow | $in.0.self < 50ms and $in.0.total >= 50ms"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_by_command_with_block_granularity_errors() {
+    let actual = nu!("debug profile --by command --granularity block { 'spam' }");
+    assert!(
+        actual
+            .err
+            .contains("`--by command` cannot be used with `--granularity block`")
+    );
+}
+
+#[test]
+fn profile_exclude_builtins_keeps_custom_calls() {
+    let actual = nu!(
+        "def spam [] { [1 2] | each { $in } }; debug profile --granularity call --exclude-builtins --max-depth 5 { spam } | get source | to nuon"
+    );
+    assert_eq!(
+        actual.out,
+        r#"["debug profile --granularity call --exclude-builtins --max-depth 5 { spam }", spam]"#
+    );
+}
+
+#[test]
+fn profile_exclude_builtins_keeps_closures_of_builtins() {
+    let actual = nu!(
+        "debug profile --exclude-builtins { [1] | each { 'eggs' } } | $in.instruction | any { $in =~ 'each' } | not $in"
+    );
+    assert_eq!(actual.out, "true");
+
+    // the closure of `each` is attributed to the root, since `each` is left out
+    let actual = nu!(
+        "debug profile --exclude-builtins { [1] | each { 'eggs' } } | where source == \"'eggs'\" | get parent_id.0"
+    );
+    assert_eq!(actual.out, "0");
+}
+
+#[test]
+fn profile_by_command_exclude_builtins_counts_builtins_as_self_time() {
+    let actual = nu!(
+        "def slow [] { sleep 50ms }; debug profile --by command --exclude-builtins --duration-values { slow } | $in.command == [slow] and $in.0.self >= 50ms"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_wall_clock_includes_waiting() {
+    let actual =
+        nu!("debug profile --clock wall --duration-values { sleep 50ms } | $in.0.duration >= 50ms");
+    assert_eq!(actual.out, "true");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn profile_cpu_clock_excludes_waiting() {
+    let actual =
+        nu!("debug profile --clock cpu --duration-values { sleep 50ms } | $in.0.duration < 50ms");
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_invalid_clock_errors() {
+    let actual = nu!("debug profile --clock sundial { 1 }");
+    assert!(actual.err.contains("one of: wall, cpu"));
+}
+
+#[test]
+fn profile_top_limits_rows() {
+    let actual = nu!("debug profile --top 3 { 1..10 | each { $in * 2 } } | length");
+    assert_eq!(actual.out, "3");
+}
+
+#[test]
+fn profile_top_sorts_by_duration() {
+    let actual = nu!(
+        "debug profile --top 5 --duration-values { 1; sleep 20ms; 2 } | $in.duration == ($in.duration | sort --reverse)"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn profile_top_by_command() {
+    let actual = nu!(
+        "debug profile --top 1 --by command { sleep 20ms; [1 2] | each { $in } } | get command | to nuon"
+    );
+    assert_eq!(actual.out, "[sleep]");
+}
+
+#[test]
+fn profile_top_must_be_positive() {
+    let actual = nu!("debug profile --top 0 { 1 }");
+    assert!(actual.err.contains("a positive int"));
+}
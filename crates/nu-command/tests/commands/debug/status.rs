@@ -0,0 +1,30 @@
+use nu_test_support::nu;
+
+#[test]
+fn status_without_debugger() {
+    let actual = nu!("debug status | to nuon");
+    assert_eq!(
+        actual.out,
+        "{active: false, kind: null, poisoned: false, counters: {}}"
+    );
+}
+
+#[test]
+fn status_inside_profile() {
+    let actual = nu!("debug profile { debug status | print; null } | ignore");
+    assert!(actual.out.contains("profiler"));
+}
+
+#[test]
+fn status_inside_trace_counts_instructions() {
+    let actual = nu!(
+        "debug trace { debug status | print $'($in.kind) ($in.counters.instructions > 0)' } | ignore"
+    );
+    assert_eq!(actual.out, "recorder true");
+}
+
+#[test]
+fn status_reset_deactivates_debugger() {
+    let actual = nu!("debug status --reset | get active");
+    assert_eq!(actual.out, "false");
+}
@@ -0,0 +1,36 @@
+use nu_test_support::nu;
+
+#[test]
+fn trace_records_nested_instructions() {
+    let actual = nu!(
+        "debug trace { do { 'spam' } } | where depth > 1 | get instruction | any { str starts-with load-literal }"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn trace_ids_follow_evaluation_order() {
+    let actual = nu!(
+        "debug trace { if true { 'spam' } } | get id | enumerate | all { $in.index == $in.item }"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn trace_values_records_outputs() {
+    let actual =
+        nu!("debug trace --values { 'spam' | str upcase } | get output | any { $in == SPAM }");
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn trace_without_values_has_no_output_column() {
+    let actual = nu!("debug trace { 'spam' } | columns | any { $in == output }");
+    assert_eq!(actual.out, "false");
+}
+
+#[test]
+fn trace_returns_closure_errors() {
+    let actual = nu!("debug trace { error make { msg: 'spam' } }");
+    assert!(actual.err.contains("spam"));
+}
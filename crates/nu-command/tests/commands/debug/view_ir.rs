@@ -0,0 +1,38 @@
+use nu_test_support::nu;
+
+#[test]
+fn view_ir_only_filters_opcodes() {
+    let actual = nu!("view ir --only [call] { ls | length } | lines | length");
+    assert_eq!(actual.out, "2");
+}
+
+#[test]
+fn view_ir_only_keeps_instruction_indices() {
+    let filtered =
+        nu!("view ir --only [return] { ls | length } | split row ':' | first | str trim");
+    let full = nu!("view ir { ls | length } | lines | last | split row ':' | first | str trim");
+    assert_eq!(filtered.out, full.out);
+}
+
+#[test]
+fn view_ir_only_json_filters_formatted_instructions() {
+    let actual = nu!(
+        "view ir --json --only [call] { ls | length } | from json | get formatted_instructions | all { str starts-with call }"
+    );
+    assert_eq!(actual.out, "true");
+}
+
+#[test]
+fn view_ir_shows_jump_targets() {
+    let actual = nu!(
+        "view ir --only [jump branch-if] { if true { 1 } else { 2 } } | lines | each { split row ' ' | last } | str join ' '"
+    );
+    assert_eq!(actual.out, "5 6");
+}
+
+#[test]
+fn view_ir_only_unknown_opcode_errors() {
+    let actual = nu!("view ir --only [call cal] { ls | length }");
+    assert!(actual.err.contains("unknown opcode 'cal'"));
+    assert!(actual.err.contains("branch-if"));
+}
@@ -0,0 +1,28 @@
+use nu_test_support::nu;
+
+#[test]
+fn watchdog_passes_through_output() {
+    let actual = nu!("debug watchdog --max-step 10sec { [1 2 3] | math sum }");
+    assert_eq!(actual.out, "6");
+}
+
+#[test]
+fn watchdog_interrupts_slow_instruction() {
+    let actual = nu!("debug watchdog --max-step 100ms { sleep 10sec; 'done' }");
+    assert!(actual.out.is_empty());
+    assert!(actual.err.contains("Watchdog timeout"));
+    assert!(actual.err.contains("ran for longer than 100ms"));
+    assert!(actual.err.contains("interrupted `call"));
+}
+
+#[test]
+fn watchdog_resets_interrupt() {
+    let actual = nu!("try { debug watchdog --max-step 100ms { sleep 10sec } }; 'after'");
+    assert_eq!(actual.out, "after");
+}
+
+#[test]
+fn watchdog_max_step_must_be_positive() {
+    let actual = nu!("debug watchdog --max-step 0sec { 'spam' }");
+    assert!(actual.err.contains("must be positive"));
+}
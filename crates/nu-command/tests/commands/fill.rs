@@ -21,3 +21,18 @@ fn string_fill_fancy() {
         "+\u{1b}[31ma\u{1b}[32me\u{308}\u{1b}[36mc\u{1b}[0m+"
     );
 }
+
+#[test]
+fn string_fill_wide_characters() {
+    let actual = nu!(r#""漢字" | fill --alignment left --character "-" --width 6"#);
+
+    assert_eq!(actual.out, "漢字--");
+}
+
+#[test]
+fn string_fill_styled_wide_characters() {
+    let actual =
+        nu!(r#"$"(ansi red)漢(ansi reset)a" | fill --alignment right --character "-" --width 5"#);
+
+    assert_eq!(actual.out, "--\u{1b}[31m漢\u{1b}[0ma");
+}
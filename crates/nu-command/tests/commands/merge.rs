@@ -1,4 +1,4 @@
-use nu_test_support::{nu, pipeline};
+use nu_test_support::{nu, pipeline, playground::Playground};
 
 #[test]
 fn row() {
@@ -89,3 +89,427 @@ fn multi_row_table_overwrite() {
         "[[a, b]; [7, 7], [8, 9], [9, 9]]"
     );
 }
+
+#[test]
+fn merge_at_creates_nested_records() {
+    let actual = nu!("{a: {}} | merge --at a.b.c 5 | to nuon");
+    assert_eq!(actual.out, "{a: {b: {c: 5}}}");
+}
+
+#[test]
+fn merge_at_deep_merges_records() {
+    let actual = nu!("{a: {b: {c: 1, d: 2}, e: 3}} | merge --at a.b {d: 4} | to nuon");
+    assert_eq!(actual.out, "{a: {b: {c: 1, d: 4}, e: 3}}");
+}
+
+#[test]
+fn merge_at_overwrites_leaf() {
+    let actual = nu!("{a: {b: 1}} | merge --at a.b [1 2] | to nuon");
+    assert_eq!(actual.out, "{a: {b: [1, 2]}}");
+}
+
+#[test]
+fn merge_at_non_record_errors() {
+    let actual = nu!("{a: 1} | merge --at a.b 5");
+    assert!(actual.err.contains("cell path"));
+}
+
+#[test]
+fn merge_non_record_without_at_errors() {
+    let actual = nu!("{a: 1} | merge 5");
+    assert!(actual.err.contains("Input type not supported"));
+    assert!(actual.err.contains("input type: record<a: int> and int"));
+}
+
+#[test]
+fn merge_at_every_row_of_table() {
+    let actual = nu!("[{a: {b: {c: 1}}} {a: {}} {}] | merge --at a.b {d: 2} | to nuon");
+    assert_eq!(
+        actual.out,
+        "[[a]; [{b: {c: 1, d: 2}}], [{b: {d: 2}}], [{b: {d: 2}}]]"
+    );
+}
+
+#[test]
+fn merge_at_table_non_record_in_path_errors() {
+    let actual = nu!("[{a: {}} {a: 1}] | merge --at a.b 5");
+    assert!(actual.err.contains("cell path"));
+}
+
+#[test]
+fn merge_at_table_non_record_row_errors() {
+    let actual = nu!("[{a: {}} 5] | merge --at a 1");
+    assert!(actual.err.contains("cell path"));
+}
+
+#[test]
+fn merge_at_empty_table() {
+    let actual = nu!("[] | merge --at a.b 5 | to nuon");
+    assert_eq!(actual.out, "[]");
+}
+
+#[test]
+fn merge_null_is_noop() {
+    let actual = nu!("[{a: 1} {a: 2}] | merge null | to nuon");
+    assert_eq!(actual.out, "[[a]; [1], [2]]");
+}
+
+#[test]
+fn merge_null_variable_is_noop() {
+    let actual = nu!("let maybe: any = null; {a: 1} | merge $maybe | to nuon");
+    assert_eq!(actual.out, "{a: 1}");
+}
+
+#[test]
+fn merge_collect_records() {
+    let actual = nu!("{a: 1, b: 2} | merge --collect {a: 3, c: 4} | to nuon");
+    assert_eq!(actual.out, "{a: [1, 3], b: 2, c: 4}");
+}
+
+#[test]
+fn merge_collect_appends_to_lists() {
+    let actual = nu!("{a: [1 2]} | merge --collect {a: 3} | merge --collect {a: 4} | to nuon");
+    assert_eq!(actual.out, "{a: [1, 2, 3, 4]}");
+}
+
+#[test]
+fn merge_collect_does_not_merge_nested_records() {
+    let actual = nu!("{a: {b: 1}} | merge --collect {a: {b: 2}} | to nuon");
+    assert_eq!(actual.out, "{a: [[b]; [1], [2]]}");
+}
+
+#[test]
+fn merge_collect_tables() {
+    let actual = nu!("[[a]; [1] [2]] | merge --collect [[a]; [3]] | to nuon");
+    assert_eq!(actual.out, "[[a]; [[1, 3]], [2]]");
+}
+
+#[test]
+fn merge_collect_with_at_errors() {
+    let actual = nu!("{a: {}} | merge --collect --at a {b: 1}");
+    assert!(actual.err.contains("--collect"));
+}
+
+#[test]
+fn merge_keys_only_keeps_values() {
+    let actual = nu!("{a: 1, c: 2} | merge --keys-only {b: 0, a: 0} | to nuon");
+    assert_eq!(actual.out, "{a: 1, c: 2, b: null}");
+}
+
+#[test]
+fn merge_keys_only_with_default() {
+    let actual =
+        nu!("[[a]; [1] [2]] | merge --keys-only --default 0 [[a b]; [5 5] [6 6]] | to nuon");
+    assert_eq!(actual.out, "[[a, b]; [1, 0], [2, 0]]");
+}
+
+#[test]
+fn merge_default_requires_keys_only() {
+    let actual = nu!("{a: 1} | merge --default 0 {b: 2}");
+    assert!(actual.err.contains("--keys-only"));
+}
+
+#[test]
+fn merge_keys_only_with_collect_errors() {
+    let actual = nu!("{a: 1} | merge --collect --keys-only {b: 2}");
+    assert!(actual.err.contains("cannot be used with"));
+}
+
+#[test]
+fn merge_zip_lists() {
+    let actual = nu!("[1 2 3] | merge --zip [a b c] | to nuon");
+    assert_eq!(actual.out, r#"[[1, a], [2, b], [3, c]]"#);
+}
+
+#[test]
+fn merge_zip_length_mismatch_errors() {
+    let actual = nu!("[1 2 3] | merge --zip [10 20]");
+    assert!(
+        actual
+            .err
+            .contains("the argument has 2 elements, but the input has 3")
+    );
+}
+
+#[test]
+fn merge_zip_requires_lists() {
+    let actual = nu!("{a: 1} | merge --zip {a: 2}");
+    assert!(actual.err.contains("both list"));
+}
+
+#[test]
+fn merge_strict_updates_known_columns() {
+    let actual = nu!("[[a b]; [1 2] [3 4]] | merge --strict [[b]; [5]] | to nuon");
+    assert_eq!(actual.out, "[[a, b]; [1, 5], [3, 4]]");
+}
+
+#[test]
+fn merge_strict_errors_on_new_columns() {
+    let actual = nu!("{a: 1} | merge --strict {a: 2, b: 3, c: 4}");
+    assert!(
+        actual
+            .err
+            .contains("unexpected columns not in the input: b, c")
+    );
+}
+
+#[test]
+fn merge_strict_with_keys_only_errors() {
+    let actual = nu!("{a: 1} | merge --strict --keys-only {a: 2}");
+    assert!(
+        actual
+            .err
+            .contains("`--strict` cannot be used with `--keys-only`")
+    );
+}
+
+#[test]
+fn merge_case_insensitive_keeps_input_casing() {
+    let actual = nu!("{Path: a, HOME: b} | merge -i {PATH: c, home: d, New: e} | to nuon");
+    assert_eq!(actual.out, "{Path: c, HOME: d, New: e}");
+}
+
+#[test]
+fn merge_case_insensitive_tables() {
+    let actual = nu!("[[Path]; [a] [b]] | merge --case-insensitive [[PATH]; [c]] | to nuon");
+    assert_eq!(actual.out, "[[Path]; [c], [b]]");
+}
+
+#[test]
+fn merge_case_sensitive_by_default() {
+    let actual = nu!("{Path: a} | merge {PATH: c} | to nuon");
+    assert_eq!(actual.out, "{Path: a, PATH: c}");
+}
+
+#[test]
+fn merge_case_insensitive_with_strict() {
+    let actual = nu!("{Path: a} | merge -i --strict {PATH: c} | to nuon");
+    assert_eq!(actual.out, "{Path: c}");
+}
+
+#[test]
+fn merge_keeps_input_content_type() {
+    let actual = nu!(
+        "{a: 1} | metadata set --content-type application/json | merge {b: 2} | metadata | get content_type"
+    );
+    assert_eq!(actual.out, "application/json");
+}
+
+#[test]
+fn merge_keeps_metadata_of_opened_file() {
+    Playground::setup("merge_keeps_metadata", |dirs, _| {
+        let actual = nu!(cwd: dirs.formats(), pipeline("
+            open sample-simple.json
+            | metadata set --content-type application/json
+            | merge ({third: 3} | metadata set --content-type text/plain)
+            | metadata
+            | $'($in.source | path basename) ($in.content_type)'
+        "));
+        assert_eq!(actual.out, "sample-simple.json application/json");
+    })
+}
+
+#[test]
+fn merge_check_reports_record_changes() {
+    let actual = nu!("{a: 1, b: 2} | merge --check {a: 1, b: 3, c: 4} | to nuon");
+    assert_eq!(
+        actual.out,
+        "[[column, change]; [a, unchanged], [b, overwritten], [c, added]]"
+    );
+}
+
+#[test]
+fn merge_check_reports_table_rows() {
+    let actual = nu!("[[a]; [1] [2]] | merge --check [[a b]; [1 5] [3 6] [7 8]] | to nuon");
+    assert_eq!(
+        actual.out,
+        "[[row, column, change]; [0, a, unchanged], [0, b, added], [1, a, overwritten], [1, b, added]]"
+    );
+}
+
+#[test]
+fn merge_check_with_collect_and_keys_only() {
+    let actual = nu!("{a: 1} | merge --check --collect {a: 2} | get change.0");
+    assert_eq!(actual.out, "collected");
+
+    let actual = nu!("{a: 1} | merge --check --keys-only {a: 2} | get change.0");
+    assert_eq!(actual.out, "kept");
+}
+
+#[test]
+fn merge_check_reports_strict_errors() {
+    let actual = nu!("{a: 1} | merge --check --strict {b: 2}");
+    assert!(
+        actual
+            .err
+            .contains("unexpected columns not in the input: b")
+    );
+}
+
+#[test]
+fn merge_check_with_at_errors() {
+    let actual = nu!("{a: {}} | merge --check --at a {b: 1}");
+    assert!(actual.err.contains("`--check` cannot be used with `--at`"));
+}
+
+#[test]
+fn merge_closure_per_row() {
+    let actual = nu!("[{a: 1} {a: 2}] | merge {|row| {b: ($row.a * 2)} } | to nuon");
+    assert_eq!(actual.out, "[[a, b]; [1, 2], [2, 4]]");
+}
+
+#[test]
+fn merge_closure_into_record() {
+    let actual = nu!("{a: 1, b: 2} | merge {|rec| {a: ($rec.a + $rec.b)} } | to nuon");
+    assert_eq!(actual.out, "{a: 3, b: 2}");
+}
+
+#[test]
+fn merge_closure_returning_null_keeps_row() {
+    let actual = nu!("[{a: 1} {a: 2}] | merge {|row| if $row.a == 2 { {b: 5} } } | to nuon");
+    assert_eq!(actual.out, "[{a: 1}, {a: 2, b: 5}]");
+}
+
+#[test]
+fn merge_closure_with_collect() {
+    let actual = nu!("[{a: 1}] | merge --collect {|row| {a: ($row.a + 1)} } | to nuon");
+    assert_eq!(actual.out, "[[a]; [[1, 2]]]");
+}
+
+#[test]
+fn merge_closure_returning_non_record_errors() {
+    let actual = nu!("[{a: 1}] | merge {|row| [1 2] }");
+    assert!(
+        actual
+            .err
+            .contains("the closure must return a record, not list<int>")
+    );
+}
+
+#[test]
+fn merge_closure_with_zip_errors() {
+    let actual = nu!("[{a: 1}] | merge --zip {|row| {b: 1} }");
+    assert!(actual.err.contains("`--zip` cannot be used with a closure"));
+}
+
+#[test]
+fn merge_fold_later_records_win() {
+    let actual = nu!("{a: 1, b: 1} | merge --fold [{a: 2, c: 2} {a: 3}] | to nuon");
+    assert_eq!(actual.out, "{a: 3, b: 1, c: 2}");
+}
+
+#[test]
+fn merge_fold_with_collect() {
+    let actual = nu!("{a: 1} | merge --fold --collect [{a: 2} {a: 3}] | to nuon");
+    assert_eq!(actual.out, "{a: [1, 2, 3]}");
+}
+
+#[test]
+fn merge_fold_empty_list_keeps_input() {
+    let actual = nu!("{a: 1} | merge --fold [] | to nuon");
+    assert_eq!(actual.out, "{a: 1}");
+}
+
+#[test]
+fn merge_fold_non_record_errors() {
+    let actual = nu!("{a: 1} | merge --fold [{a: 2} 5]");
+    assert!(
+        actual
+            .err
+            .contains("`--fold` needs a list of records, but element 1 is int")
+    );
+}
+
+#[test]
+fn merge_fold_non_list_errors() {
+    let actual = nu!("{a: 1} | merge --fold {a: 2}");
+    assert!(
+        actual
+            .err
+            .contains("`--fold` needs a list of records, not record")
+    );
+}
+
+#[test]
+fn merge_fold_with_at_errors() {
+    let actual = nu!("{a: {}} | merge --fold --at a [{b: 1}]");
+    assert!(actual.err.contains("`--at` cannot be used with `--fold`"));
+}
+
+#[test]
+fn merge_only_matching_columns() {
+    let actual = nu!("{a: 1} | merge --only 'x_*' {x_b: 2, x_c: 3, d: 4} | to nuon");
+    assert_eq!(actual.out, "{a: 1, x_b: 2, x_c: 3}");
+}
+
+#[test]
+fn merge_except_matching_columns() {
+    let actual = nu!("{a: 1} | merge --except 'x_*' {x_b: 2, d: 4} | to nuon");
+    assert_eq!(actual.out, "{a: 1, d: 4}");
+}
+
+#[test]
+fn merge_only_and_except() {
+    let actual = nu!("{} | merge --only 'x_*' --except '*_c' {x_b: 2, x_c: 3, d: 4} | to nuon");
+    assert_eq!(actual.out, "{x_b: 2}");
+}
+
+#[test]
+fn merge_only_keeps_input_columns() {
+    let actual = nu!("{d: 1} | merge --only 'x_*' {x_b: 2, d: 4} | to nuon");
+    assert_eq!(actual.out, "{d: 1, x_b: 2}");
+}
+
+#[test]
+fn merge_only_table() {
+    let actual = nu!("[{a: 1} {a: 2}] | merge --only 'b' [{b: 3, c: 4} {b: 5}] | to nuon");
+    assert_eq!(actual.out, "[[a, b]; [1, 3], [2, 5]]");
+}
+
+#[test]
+fn merge_only_case_insensitive() {
+    let actual = nu!("{} | merge --only 'x*' --case-insensitive {X: 1, y: 2} | to nuon");
+    assert_eq!(actual.out, "{X: 1}");
+}
+
+#[test]
+fn merge_only_with_strict_checks_selected_columns() {
+    let actual = nu!("{a: 1} | merge --strict --only 'a' {a: 2, b: 3} | to nuon");
+    assert_eq!(actual.out, "{a: 2}");
+}
+
+#[test]
+fn merge_only_invalid_glob_errors() {
+    let actual = nu!("{a: 1} | merge --only '[' {a: 2}");
+    assert!(actual.err.contains("Invalid glob pattern"));
+}
+
+#[test]
+fn merge_only_with_zip_errors() {
+    let actual = nu!("[{a: 1}] | merge --zip --only a [{a: 2}]");
+    assert!(actual.err.contains("`--only` cannot be used with `--zip`"));
+}
+
+#[test]
+fn merge_keeps_span_of_input_rows() {
+    let actual = nu!(
+        "let merged = [{a: 1}] | merge [{b: 2}]; let span = ($merged.0 | metadata).span; view span $span.start $span.end"
+    );
+    assert_eq!(actual.out, "{a: 1}");
+}
+
+#[test]
+fn merge_keeps_span_of_winning_value() {
+    let actual = nu!(
+        "let merged = {a: 1, b: 2} | merge {a: 33}; [a b] | each {|col| let span = ($merged | get $col | metadata).span; view span $span.start $span.end } | to nuon"
+    );
+    assert_eq!(actual.out, "[\"33\", \"2\"]");
+}
+
+#[test]
+fn merge_deep_keeps_span_of_nested_record() {
+    let actual = nu!(
+        "let merged = {a: {b: 1}} | merge deep {a: {c: 2}}; let span = ($merged.a | metadata).span; view span $span.start $span.end"
+    );
+    assert_eq!(actual.out, "{b: 1}");
+}
@@ -4,7 +4,7 @@ use nu_test_support::nu;
 fn table_strategy_table() {
     assert_eq!(
         nu!(
-            "{} | merge deep {} | to nuon",
+            "{} | merge deep --keep-extra {} | to nuon",
             "{inner: [{a: 1}, {b: 2}]}",
             "{inner: [{c: 3}]}"
         )
@@ -142,3 +142,209 @@ fn multi_row_table() {
         "[[a, b]; [{inner: {foo: abc, bar: def}}, {inner: {baz: ghi, qux: jkl}}]]"
     )
 }
+
+#[test]
+fn skip_empty_keeps_input_values() {
+    assert_eq!(
+        nu!(
+            "{} | merge deep --skip-empty {} | to nuon",
+            "{a: 1, b: abc, c: [1], d: {e: 2}}",
+            "{a: null, b: '', c: [], d: {}}"
+        )
+        .out,
+        "{a: 1, b: abc, c: [1], d: {e: 2}}"
+    )
+}
+
+#[test]
+fn skip_empty_nested() {
+    assert_eq!(
+        nu!(
+            "{} | merge deep --skip-empty {} | to nuon",
+            "{a: {b: abc, c: [1, 2]}}",
+            "{a: {b: '', c: [3]}}"
+        )
+        .out,
+        "{a: {b: abc, c: [3]}}"
+    )
+}
+
+#[test]
+fn skip_empty_adds_new_keys() {
+    assert_eq!(
+        nu!(
+            "{} | merge deep --skip-empty {} | to nuon",
+            "{a: 1}",
+            "{b: [], c: 0, d: false}"
+        )
+        .out,
+        "{a: 1, b: [], c: 0, d: false}"
+    )
+}
+
+#[test]
+fn without_skip_empty_overwrites() {
+    assert_eq!(
+        nu!(
+            "{} | merge deep {} | to nuon",
+            "{a: 1, b: abc, c: [1]}",
+            "{a: null, b: '', c: []}"
+        )
+        .out,
+        "{a: null, b: \"\", c: []}"
+    )
+}
+
+#[test]
+fn max_depth_one() {
+    assert_eq!(
+        nu!(
+            "{} | merge deep --max-depth 1 {} | to nuon",
+            "{a: {b: 1, c: {d: 2}}, e: 3}",
+            "{a: {c: {f: 4}}}"
+        )
+        .out,
+        "{a: {c: {f: 4}}, e: 3}"
+    )
+}
+
+#[test]
+fn max_depth_two() {
+    assert_eq!(
+        nu!(
+            "{} | merge deep --max-depth 2 {} | to nuon",
+            "{a: {b: 1, c: {d: 2}}, e: 3}",
+            "{a: {c: {f: 4}}}"
+        )
+        .out,
+        "{a: {b: 1, c: {f: 4}}, e: 3}"
+    )
+}
+
+#[test]
+fn max_depth_beyond_nesting() {
+    assert_eq!(
+        nu!(
+            "{} | merge deep --max-depth 10 {} | to nuon",
+            "{a: {b: 1, c: {d: 2}}, e: 3}",
+            "{a: {c: {f: 4}}}"
+        )
+        .out,
+        "{a: {b: 1, c: {d: 2, f: 4}}, e: 3}"
+    )
+}
+
+#[test]
+fn max_depth_zero_errors() {
+    let actual = nu!("{a: 1} | merge deep --max-depth 0 {b: 2}");
+    assert!(actual.err.contains("use a value other than 0"));
+}
+
+#[test]
+fn table_different_lengths_errors() {
+    let actual = nu!("[{a: 1} {a: 2}] | merge deep [{b: 3}]");
+    assert!(
+        actual
+            .err
+            .contains("cannot merge a table with 1 rows into a table with 2 rows")
+    );
+}
+
+#[test]
+fn keep_extra_longer_left() {
+    assert_eq!(
+        nu!("[{a: 1} {a: 2}] | merge deep --keep-extra [{b: 3}] | to nuon").out,
+        "[{a: 1, b: 3}, {a: 2}]"
+    )
+}
+
+#[test]
+fn keep_extra_longer_right() {
+    assert_eq!(
+        nu!("[{a: 1}] | merge deep --keep-extra [{b: 2} {b: 3}] | to nuon").out,
+        "[{a: 1, b: 2}, {b: 3}]"
+    )
+}
+
+#[test]
+fn equal_length_tables_merge_without_keep_extra() {
+    assert_eq!(
+        nu!("[{a: 1} {a: 2}] | merge deep [{b: 3} {b: 4}] | to nuon").out,
+        "[[a, b]; [1, 3], [2, 4]]"
+    )
+}
+
+#[test]
+fn sum_ints() {
+    assert_eq!(
+        nu!("{hits: 3, name: a} | merge deep --sum {hits: 5, name: b} | to nuon").out,
+        "{hits: 8, name: b}"
+    )
+}
+
+#[test]
+fn sum_float_and_int() {
+    assert_eq!(
+        nu!("{a: {total: 1.5}} | merge deep --sum {a: {total: 2}} | to nuon").out,
+        "{a: {total: 3.5}}"
+    )
+}
+
+#[test]
+fn sum_tables() {
+    assert_eq!(
+        nu!("[[hits]; [1] [2]] | merge deep --sum [[hits]; [10] [20]] | to nuon").out,
+        "[[hits]; [11], [22]]"
+    )
+}
+
+#[test]
+fn sum_with_null_counts_as_zero() {
+    assert_eq!(
+        nu!("{a: null, b: 2} | merge deep --sum {a: 1, b: null} | to nuon").out,
+        "{a: 1, b: 2}"
+    )
+}
+
+#[test]
+fn sum_int_and_string_errors() {
+    let actual = nu!("{hits: 3} | merge deep --sum {hits: five}");
+    assert!(actual.err.contains("cannot sum int and string"));
+}
+
+#[test]
+fn without_sum_overwrites_numbers() {
+    assert_eq!(
+        nu!("{hits: 3} | merge deep {hits: 5} | to nuon").out,
+        "{hits: 5}"
+    )
+}
+
+#[test]
+fn fold_merges_records_in_order() {
+    assert_eq!(
+        nu!("{a: {x: 1, y: 1}} | merge deep --fold [{a: {x: 2}} {a: {x: 3, z: 3}}] | to nuon").out,
+        "{a: {x: 3, y: 1, z: 3}}"
+    )
+}
+
+#[test]
+fn fold_respects_strategy() {
+    assert_eq!(
+        nu!(
+            "{tags: [a]} | merge deep --fold --strategy append [{tags: [b]} {tags: [c]}] | to nuon"
+        )
+        .out,
+        "{tags: [a, b, c]}"
+    )
+}
+
+#[test]
+fn fold_non_record_errors() {
+    let actual = nu!("{a: 1} | merge deep --fold [{a: 2} [3]]");
+    assert!(
+        actual
+            .err
+            .contains("`--fold` needs a list of records, but element 1 is list<int>")
+    );
+}
@@ -421,3 +421,12 @@ fn test_redirection_trim() {
 
     assert_eq!(actual.out, "7");
 }
+
+#[test]
+fn str_visible_slice_keeps_styling() {
+    let actual = nu!(
+        "$'(ansi red)good nushell(ansi reset)' | str visible-slice 5..6 | ($in | str starts-with (ansi red)) and ($in | ansi strip) == nu"
+    );
+
+    assert_eq!(actual.out, "true");
+}
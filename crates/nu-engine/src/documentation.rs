@@ -1,8 +1,8 @@
 use crate::eval_call;
 use fancy_regex::{Captures, Regex};
 use nu_protocol::{
-    Category, Config, IntoPipelineData, PipelineData, PositionalArg, Signature, Span, SpanId,
-    Spanned, SyntaxShape, Type, Value,
+    Category, Config, IntoPipelineData, PipelineData, PositionalArg, ShellError, Signature, Span,
+    SpanId, Spanned, SyntaxShape, Type, Value,
     ast::{Argument, Call, Expr, Expression, RecordItem},
     debugger::WithoutDebug,
     engine::CommandType,
@@ -94,8 +94,16 @@ fn try_nu_highlight(
     reject_garbage: bool,
     engine_state: &EngineState,
     stack: &mut Stack,
-) -> Option<String> {
-    let highlighter = engine_state.find_decl(b"nu-highlight", &[])?;
+) -> Result<String, ShellError> {
+    let highlighter = engine_state
+        .find_decl(b"nu-highlight", &[])
+        .ok_or_else(|| ShellError::GenericError {
+            error: "Could not highlight code".into(),
+            msg: "the `nu-highlight` command is not available".into(),
+            span: None,
+            help: None,
+            inner: vec![],
+        })?;
 
     let decl = engine_state.get_decl(highlighter);
     let mut call = Call::new(Span::unknown());
@@ -117,14 +125,27 @@ fn try_nu_highlight(
         Value::string(code_string, Span::unknown()).into_pipeline_data(),
     )
     .and_then(|pipe| pipe.into_value(Span::unknown()))
-    .and_then(|val| val.coerce_into_string())
-    .ok()
+    .and_then(|val| match val {
+        Value::Error { error, .. } => Err(*error),
+        val => val.coerce_into_string(),
+    })
 }
 
 /// Syntax highlight code using the `nu-highlight` command if available, falling back to the given string
 fn nu_highlight_string(code_string: &str, engine_state: &EngineState, stack: &mut Stack) -> String {
     try_nu_highlight(code_string, false, engine_state, stack)
-        .unwrap_or_else(|| code_string.to_string())
+        .unwrap_or_else(|_| code_string.to_string())
+}
+
+/// Get the code of a capture group, without the surrounding backticks
+fn capture_group_code<'t>(captures: &Captures<'t>) -> Option<&'t str> {
+    // like in Markdown, one space is stripped from both ends, so code can start or end with a backtick
+    let content = captures.name("code")?.as_str();
+    let content = match content.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+        Some(stripped) if !stripped.trim().is_empty() => stripped,
+        _ => content,
+    };
+    Some(content)
 }
 
 /// Apply code highlighting to code in a capture group
@@ -133,11 +154,41 @@ fn highlight_capture_group(
     engine_state: &EngineState,
     stack: &mut Stack,
 ) -> String {
-    let Some(content) = captures.get(1) else {
+    let Some(content) = capture_group_code(captures) else {
         // this shouldn't happen
         return String::new();
     };
 
+    // Use fallback style if highlight failed/syntax was invalid
+    highlight_code_content(content, engine_state, stack)
+        .unwrap_or_else(|_| highlight_fallback(content))
+}
+
+/// Apply code highlighting to code in a capture group, only using the fallback style on invalid
+/// syntax
+fn try_highlight_capture_group(
+    captures: &Captures,
+    engine_state: &EngineState,
+    stack: &mut Stack,
+) -> Result<String, ShellError> {
+    let Some(content) = capture_group_code(captures) else {
+        // this shouldn't happen
+        return Ok(String::new());
+    };
+
+    match highlight_code_content(content, engine_state, stack) {
+        // `nu-highlight --reject-garbage` uses this error for invalid syntax
+        Err(ShellError::OutsideSpannedLabeledError { .. }) => Ok(highlight_fallback(content)),
+        highlighted => highlighted,
+    }
+}
+
+/// Highlight code with `nu-highlight`, rejecting invalid syntax
+fn highlight_code_content(
+    content: &str,
+    engine_state: &EngineState,
+    stack: &mut Stack,
+) -> Result<String, ShellError> {
     // Save current color config
     let config_old = stack.get_config(engine_state);
     let mut config = (*config_old).clone();
@@ -160,7 +211,7 @@ fn highlight_capture_group(
     stack.config = Some(Arc::new(config));
 
     // Highlight and reject invalid syntax
-    let highlighted = try_nu_highlight(content.into(), true, engine_state, stack)
+    let highlighted = try_nu_highlight(content, true, engine_state, stack)
         // // Make highlighted string italic
         .map(|text| {
             let resets = text.match_indices(RESET).count();
@@ -173,8 +224,7 @@ fn highlight_capture_group(
     // Restore original config
     stack.config = Some(config_old);
 
-    // Use fallback style if highlight failed/syntax was invalid
-    highlighted.unwrap_or_else(|| highlight_fallback(content.into()))
+    highlighted
 }
 
 /// Apply fallback code style
@@ -182,9 +232,22 @@ fn highlight_fallback(text: &str) -> String {
     format!("{DEFAULT_DIMMED}{DEFAULT_ITALIC}{text}{RESET}")
 }
 
+/// Pattern matching code within backticks
+// See [`tests::test_code_formatting`] for examples
+static CODE_PATTERN: &str = r"(?x)     # verbose mode
+        (?<![\p{Letter}\d`\\])  # negative look-behind for alphanumeric: ensure backticks are not directly preceded by letter/number.
+                               # also excludes longer runs of backticks and escaped backticks.
+        (?<ticks>`+)(?!`)      # opening run of any number of backticks.
+        (?<code>[^\n]+?)       # capture characters inside backticks, excluding newlines. ungreedy.
+        (?<!`)\k<ticks>(?!`)   # closing run of exactly as many backticks as the opening run.
+        (?![\p{Letter}\d])     # negative look-ahead for alphanumeric: ensure backticks are not directly followed by letter/number.
+    ";
+static CODE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(CODE_PATTERN).expect("valid regex"));
+
 /// Highlight code within backticks
 ///
-/// Will attempt to use nu-highlight, falling back to dimmed and italic on invalid syntax
+/// Will attempt to use nu-highlight, falling back to dimmed and italic on invalid syntax or if
+/// highlighting failed. See [`try_highlight_code`] to get the error instead.
 fn highlight_code<'a>(
     text: &'a str,
     engine_state: &EngineState,
@@ -195,19 +258,49 @@ fn highlight_code<'a>(
         return Cow::Borrowed(text);
     }
 
-    // See [`tests::test_code_formatting`] for examples
-    static PATTERN: &str = r"(?x)     # verbose mode
-        (?<![\p{Letter}\d])    # negative look-behind for alphanumeric: ensure backticks are not directly preceded by letter/number.
-        `
-        ([^`\n]+?)           # capture characters inside backticks, excluding backticks and newlines. ungreedy.
-        `
-        (?![\p{Letter}\d])     # negative look-ahead for alphanumeric: ensure backticks are not directly followed by letter/number.
-    ";
-    static RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(PATTERN).expect("valid regex"));
-
     let do_try_highlight =
         |captures: &Captures| highlight_capture_group(captures, engine_state, stack);
-    RE.replace_all(text, do_try_highlight)
+    CODE_RE.replace_all(text, do_try_highlight)
+}
+
+/// Highlight code within backticks, returning an error if highlighting failed
+///
+/// Like [`highlight_code`], code with invalid syntax uses the fallback style. But if `nu-highlight`
+/// is not available or fails, the error is returned.
+pub fn try_highlight_code(
+    text: &str,
+    engine_state: &EngineState,
+    stack: &mut Stack,
+) -> Result<String, ShellError> {
+    let config = stack.get_config(engine_state);
+    if !config.use_ansi_coloring.get(engine_state) {
+        return Ok(text.to_string());
+    }
+
+    let mut highlighted = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for captures in CODE_RE.captures_iter(text) {
+        let captures = captures.map_err(|err| ShellError::GenericError {
+            error: "Could not highlight code".into(),
+            msg: err.to_string(),
+            span: None,
+            help: None,
+            inner: vec![],
+        })?;
+        let Some(code) = captures.get(0) else {
+            continue;
+        };
+        highlighted.push_str(&text[last_end..code.start()]);
+        highlighted.push_str(&try_highlight_capture_group(
+            &captures,
+            engine_state,
+            stack,
+        )?);
+        last_end = code.end();
+    }
+    highlighted.push_str(&text[last_end..]);
+
+    Ok(highlighted)
 }
 
 fn get_alias_documentation(
@@ -877,12 +970,102 @@ mod tests {
             Cow::Owned(_)
         ));
 
-        // no match: only characters inside backticks are backticks
-        // (the regex sees two backtick pairs with a single backtick inside, which doesn't qualify)
+        // no match: code blocks span multiple lines
         let haystack = "```\ncode block\n```";
         assert!(matches!(
             highlight_code(haystack, &engine_state, &mut stack),
             Cow::Borrowed(_)
         ));
+
+        // no match: escaped backticks
+        let haystack = r"not \`code\` at all";
+        assert!(matches!(
+            highlight_code(haystack, &engine_state, &mut stack),
+            Cow::Borrowed(_)
+        ));
+
+        // no match: runs of backticks of different lengths
+        let haystack = "``not code`";
+        assert!(matches!(
+            highlight_code(haystack, &engine_state, &mut stack),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_code_formatting_backtick_runs() {
+        let mut engine_state = EngineState::new();
+        let mut stack = Stack::new();
+
+        // force coloring on for test
+        let mut config = (*engine_state.config).clone();
+        config.use_ansi_coloring = UseAnsiColoring::True;
+        engine_state.config = Arc::new(config);
+
+        // there is no `nu-highlight` command here, so code uses the fallback style
+        let mut highlight =
+            |haystack| highlight_code(haystack, &engine_state, &mut stack).into_owned();
+
+        // single backticks
+        assert_eq!(
+            highlight("run `foo`"),
+            format!("run {}", highlight_fallback("foo"))
+        );
+
+        // double backticks
+        assert_eq!(
+            highlight("run ``foo``"),
+            format!("run {}", highlight_fallback("foo"))
+        );
+
+        // backticks embedded in double backticks
+        assert_eq!(
+            highlight("run `` echo `date` ``!"),
+            format!("run {}!", highlight_fallback("echo `date`"))
+        );
+        assert_eq!(
+            highlight("the `` ` `` character"),
+            format!("the {} character", highlight_fallback("`"))
+        );
+
+        // several code spans on one line
+        assert_eq!(
+            highlight("`a` and ``b`c``"),
+            format!(
+                "{} and {}",
+                highlight_fallback("a"),
+                highlight_fallback("b`c")
+            )
+        );
+    }
+
+    #[test]
+    fn test_try_highlight_code() {
+        let mut engine_state = EngineState::new();
+        let mut stack = Stack::new();
+
+        // without coloring, there is nothing to highlight
+        assert_eq!(
+            try_highlight_code("run `foo`", &engine_state, &mut stack).ok(),
+            Some("run `foo`".into())
+        );
+
+        // force coloring on for test
+        let mut config = (*engine_state.config).clone();
+        config.use_ansi_coloring = UseAnsiColoring::True;
+        engine_state.config = Arc::new(config);
+
+        // no code, so `nu-highlight` is not needed
+        assert_eq!(
+            try_highlight_code("no code here", &engine_state, &mut stack).ok(),
+            Some("no code here".into())
+        );
+
+        // there is no `nu-highlight` command here, unlike `highlight_code` this is an error
+        assert!(try_highlight_code("run `foo`", &engine_state, &mut stack).is_err());
+        assert_eq!(
+            highlight_code("run `foo`", &engine_state, &mut stack),
+            format!("run {}", highlight_fallback("foo"))
+        );
     }
 }
@@ -200,17 +200,20 @@ fn eval_ir_block_impl<D: DebugContext>(
         let span = &ir_block.spans[pc];
         let ast = &ir_block.ast[pc];
 
-        D::enter_instruction(ctx.engine_state, ir_block, pc, ctx.registers);
+        let debug_instruction =
+            D::enter_instruction(ctx.engine_state, ir_block, pc, ctx.registers, ctx.stack);
 
         let result = eval_instruction::<D>(ctx, instruction, span, ast, need_backtrace);
 
-        D::leave_instruction(
-            ctx.engine_state,
-            ir_block,
-            pc,
-            ctx.registers,
-            result.as_ref().err(),
-        );
+        if debug_instruction {
+            D::leave_instruction(
+                ctx.engine_state,
+                ir_block,
+                pc,
+                ctx.registers,
+                result.as_ref().err(),
+            );
+        }
 
         match result {
             Ok(InstructionResult::Continue) => {
@@ -340,7 +340,9 @@ impl InterfaceManager for EngineInterfaceManager {
             }
             PluginInput::Signal(action) => {
                 match action {
-                    SignalAction::Interrupt => self.state.signals.trigger(),
+                    SignalAction::Interrupt => {
+                        self.state.signals.trigger();
+                    }
                     SignalAction::Reset => self.state.signals.reset(),
                 }
                 self.state.signal_handlers.run(action);
@@ -6,6 +6,8 @@ use crate::{self as nu_protocol};
 pub enum ErrorStyle {
     Plain,
     Fancy,
+    /// One JSON object per error, for tools reading the errors of nushell
+    Json,
 }
 
 impl FromStr for ErrorStyle {
@@ -15,7 +17,8 @@ impl FromStr for ErrorStyle {
         match s.to_ascii_lowercase().as_str() {
             "fancy" => Ok(Self::Fancy),
             "plain" => Ok(Self::Plain),
-            _ => Err("'fancy' or 'plain'"),
+            "json" => Ok(Self::Json),
+            _ => Err("'fancy', 'plain' or 'json'"),
         }
     }
 }
@@ -26,6 +26,31 @@ pub enum TableMode {
     Double,
 }
 
+impl TableMode {
+    /// The names of all table modes accepted by [`FromStr`], including `default`
+    pub const NAMES: &[&str] = &[
+        "basic",
+        "compact",
+        "compact_double",
+        "default",
+        "heavy",
+        "light",
+        "none",
+        "reinforced",
+        "rounded",
+        "thin",
+        "with_love",
+        "psql",
+        "markdown",
+        "dots",
+        "restructured",
+        "ascii_rounded",
+        "basic_compact",
+        "single",
+        "double",
+    ];
+}
+
 impl FromStr for TableMode {
     type Err = &'static str;
 
@@ -454,3 +479,15 @@ impl UpdateFromValue for TableConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_mode_names_parse() {
+        for name in TableMode::NAMES {
+            assert!(name.parse::<TableMode>().is_ok(), "{name} should parse");
+        }
+    }
+}
@@ -14,9 +14,9 @@
 //! `Debugger::report()` to get some output from the debugger, if necessary.
 
 use crate::{
-    PipelineData, PipelineExecutionData, ShellError, Span, Value,
+    PipelineData, PipelineExecutionData, Record, ShellError, Span, Value,
     ast::{Block, PipelineElement},
-    engine::EngineState,
+    engine::{EngineState, Stack},
     ir::IrBlock,
 };
 use std::{fmt::Debug, ops::DerefMut};
@@ -50,13 +50,18 @@ pub trait DebugContext: Clone + Copy + Debug {
     }
 
     /// Called before the IR evaluator runs an instruction
+    ///
+    /// Returns whether the debugger entered the instruction, in which case `leave_instruction()`
+    /// has to be called after it runs.
     #[allow(unused_variables)]
     fn enter_instruction(
         engine_state: &EngineState,
         ir_block: &IrBlock,
         instruction_index: usize,
         registers: &[PipelineExecutionData],
-    ) {
+        stack: &Stack,
+    ) -> bool {
+        false
     }
 
     /// Called after the IR evaluator runs an instruction
@@ -113,15 +118,23 @@ impl DebugContext for WithDebug {
         ir_block: &IrBlock,
         instruction_index: usize,
         registers: &[PipelineExecutionData],
-    ) {
-        if let Ok(mut debugger) = engine_state.debugger.lock() {
-            debugger.deref_mut().enter_instruction(
-                engine_state,
-                ir_block,
-                instruction_index,
-                registers,
-            )
+        stack: &Stack,
+    ) -> bool {
+        // ask and enter under the same lock, so skipped instructions cost a single lock
+        let Ok(mut debugger) = engine_state.debugger.lock() else {
+            return false;
+        };
+        if !debugger.should_enter_instruction(engine_state, ir_block, instruction_index) {
+            return false;
         }
+        debugger.deref_mut().enter_instruction_with_stack(
+            engine_state,
+            ir_block,
+            instruction_index,
+            registers,
+            stack,
+        );
+        true
     }
 
     fn leave_instruction(
@@ -188,6 +201,21 @@ pub trait Debugger: Send + Debug {
     ) {
     }
 
+    /// Called before the IR evaluator runs an instruction, to decide whether
+    /// `enter_instruction()` and `leave_instruction()` are called for it
+    ///
+    /// Debuggers only interested in some instructions (e.g. at breakpoints) can return `false` to
+    /// skip the others cheaply.
+    #[allow(unused_variables)]
+    fn should_enter_instruction(
+        &self,
+        engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+    ) -> bool {
+        true
+    }
+
     /// Called before the IR evaluator runs an instruction
     #[allow(unused_variables)]
     fn enter_instruction(
@@ -199,6 +227,23 @@ pub trait Debugger: Send + Debug {
     ) {
     }
 
+    /// Called before the IR evaluator runs an instruction, with the stack the instruction runs on
+    ///
+    /// This is what the evaluator calls. Debuggers which inspect variables or the environment at
+    /// an instruction implement this one instead of `enter_instruction()`, which it calls by
+    /// default.
+    #[allow(unused_variables)]
+    fn enter_instruction_with_stack(
+        &mut self,
+        engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+        registers: &[PipelineExecutionData],
+        stack: &Stack,
+    ) {
+        self.enter_instruction(engine_state, ir_block, instruction_index, registers)
+    }
+
     /// Called after the IR evaluator runs an instruction
     #[allow(unused_variables)]
     fn leave_instruction(
@@ -218,6 +263,19 @@ pub trait Debugger: Send + Debug {
     fn report(&self, engine_state: &EngineState, debugger_span: Span) -> Result<Value, ShellError> {
         Ok(Value::nothing(debugger_span))
     }
+
+    /// Short name of the kind of debugger, shown by `debug status`
+    fn name(&self) -> &str {
+        "unknown"
+    }
+
+    /// Counters describing the progress of the debugger, shown by `debug status`
+    ///
+    /// Unlike report(), this can be called while the debugger is active.
+    #[allow(unused_variables)]
+    fn status(&self, debugger_span: Span) -> Record {
+        Record::new()
+    }
 }
 
 /// A debugger that does nothing
@@ -226,4 +284,153 @@ pub trait Debugger: Send + Debug {
 #[derive(Debug)]
 pub struct NoopDebugger;
 
-impl Debugger for NoopDebugger {}
+impl Debugger for NoopDebugger {
+    fn name(&self) -> &str {
+        "noop"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VarId;
+    use std::sync::Arc;
+
+    fn test_ir_block() -> IrBlock {
+        IrBlock {
+            instructions: vec![],
+            spans: vec![],
+            data: Arc::new([]),
+            ast: vec![],
+            comments: vec![],
+            register_count: 0,
+            file_count: 0,
+        }
+    }
+
+    /// Only implements the hook without the stack, like debuggers written before it existed
+    #[derive(Debug, Default)]
+    struct CountingDebugger {
+        entered: usize,
+    }
+
+    impl Debugger for CountingDebugger {
+        fn enter_instruction(
+            &mut self,
+            _engine_state: &EngineState,
+            _ir_block: &IrBlock,
+            _instruction_index: usize,
+            _registers: &[PipelineExecutionData],
+        ) {
+            self.entered += 1;
+        }
+    }
+
+    /// Reports the value a variable had when the last instruction was entered
+    #[derive(Debug)]
+    struct VariableDebugger {
+        var_id: VarId,
+        value: Option<Value>,
+    }
+
+    impl Debugger for VariableDebugger {
+        fn enter_instruction_with_stack(
+            &mut self,
+            _engine_state: &EngineState,
+            _ir_block: &IrBlock,
+            _instruction_index: usize,
+            _registers: &[PipelineExecutionData],
+            stack: &Stack,
+        ) {
+            self.value = stack.get_var(self.var_id, Span::test_data()).ok();
+        }
+
+        fn report(&self, _engine_state: &EngineState, span: Span) -> Result<Value, ShellError> {
+            Ok(self.value.clone().unwrap_or(Value::nothing(span)))
+        }
+    }
+
+    #[test]
+    fn enter_instruction_with_stack_defaults_to_enter_instruction() {
+        let engine_state = EngineState::new();
+        let mut debugger = CountingDebugger::default();
+        debugger.enter_instruction_with_stack(
+            &engine_state,
+            &test_ir_block(),
+            0,
+            &[],
+            &Stack::new(),
+        );
+        assert_eq!(debugger.entered, 1);
+    }
+
+    /// Skips every instruction
+    #[derive(Debug)]
+    struct SkippingDebugger;
+
+    impl Debugger for SkippingDebugger {
+        fn should_enter_instruction(
+            &self,
+            _engine_state: &EngineState,
+            _ir_block: &IrBlock,
+            _instruction_index: usize,
+        ) -> bool {
+            false
+        }
+
+        fn enter_instruction(
+            &mut self,
+            _engine_state: &EngineState,
+            _ir_block: &IrBlock,
+            _instruction_index: usize,
+            _registers: &[PipelineExecutionData],
+        ) {
+            panic!("skipped instructions must not be entered");
+        }
+    }
+
+    #[test]
+    fn with_debug_skips_instructions_the_debugger_does_not_enter() {
+        let engine_state = EngineState::new();
+        engine_state
+            .activate_debugger(Box::new(SkippingDebugger))
+            .expect("debugger should activate");
+        let entered =
+            WithDebug::enter_instruction(&engine_state, &test_ir_block(), 0, &[], &Stack::new());
+        engine_state
+            .deactivate_debugger()
+            .expect("debugger should deactivate");
+
+        assert!(!entered);
+    }
+
+    #[test]
+    fn with_debug_passes_the_stack_to_the_debugger() {
+        let engine_state = EngineState::new();
+        let var_id = VarId::new(0);
+        let mut stack = Stack::new();
+        stack.add_var(var_id, Value::test_int(42));
+
+        engine_state
+            .activate_debugger(Box::new(VariableDebugger {
+                var_id,
+                value: None,
+            }))
+            .expect("debugger should activate");
+        assert!(WithDebug::enter_instruction(
+            &engine_state,
+            &test_ir_block(),
+            0,
+            &[],
+            &stack
+        ));
+        let debugger = engine_state
+            .deactivate_debugger()
+            .expect("debugger should deactivate");
+
+        assert_eq!(
+            debugger.report(&engine_state, Span::test_data()).unwrap(),
+            Value::test_int(42)
+        );
+    }
+}
@@ -1,6 +1,10 @@
 //! Module containing the trait to instrument the engine for debugging and profiling
 pub mod debugger_trait;
 pub mod profiler;
+pub mod recorder;
+pub mod watchdog;
 
 pub use debugger_trait::*;
 pub use profiler::*;
+pub use recorder::*;
+pub use watchdog::*;
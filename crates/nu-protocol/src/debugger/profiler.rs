@@ -4,14 +4,14 @@
 //! profiling Nushell code.
 
 use crate::{
-    PipelineData, PipelineExecutionData, ShellError, Span, Value,
+    DeclId, PipelineData, PipelineExecutionData, Record, ShellError, Span, Value,
     ast::{Block, Expr, PipelineElement},
     debugger::Debugger,
     engine::EngineState,
-    ir::IrBlock,
+    ir::{Instruction, IrBlock},
     record,
 };
-use std::{borrow::Borrow, io::BufRead};
+use std::{borrow::Borrow, cell::Cell, collections::HashMap, io::BufRead, time::Duration};
 use web_time::Instant;
 
 #[derive(Debug, Clone, Copy)]
@@ -20,29 +20,111 @@ struct ElementId(usize);
 /// Stores profiling information about one pipeline element
 #[derive(Debug, Clone)]
 struct ElementInfo {
-    start: Instant,
+    start: Timestamp,
     duration_ns: i64,
     depth: i64,
     element_span: Span,
     element_output: Option<Value>,
     expr: Option<String>,
     instruction: Option<(usize, String)>,
+    /// The called command, if the element is a call instruction
+    decl_id: Option<DeclId>,
     children: Vec<ElementId>,
 }
 
 impl ElementInfo {
-    pub fn new(depth: i64, element_span: Span) -> Self {
+    pub fn new(depth: i64, element_span: Span, clock: ProfilerClock) -> Self {
         ElementInfo {
-            start: Instant::now(),
+            start: Timestamp::now(clock),
             duration_ns: 0,
             depth,
             element_span,
             element_output: None,
             expr: None,
             instruction: None,
+            decl_id: None,
             children: vec![],
         }
     }
+
+    /// Identifies the code an element was created from, which repeats when code is re-entered.
+    fn identity(&self) -> (usize, usize, Option<usize>) {
+        (
+            self.element_span.start,
+            self.element_span.end,
+            self.instruction.as_ref().map(|(index, _)| *index),
+        )
+    }
+}
+
+/// A point in time, measured with the clock chosen by [`ProfilerOptions::clock`]
+#[derive(Debug, Clone, Copy)]
+enum Timestamp {
+    Wall(Instant),
+    /// CPU time used by the current thread
+    Cpu(Duration),
+}
+
+impl Timestamp {
+    /// The current time of `clock`, falling back to wall time if it's not available
+    fn now(clock: ProfilerClock) -> Self {
+        match clock {
+            ProfilerClock::Cpu => thread_cpu_time()
+                .map(Timestamp::Cpu)
+                .unwrap_or_else(|| Timestamp::Wall(Instant::now())),
+            ProfilerClock::Wall => Timestamp::Wall(Instant::now()),
+        }
+    }
+
+    /// Nanoseconds passed since this timestamp, on the same clock
+    fn elapsed_ns(&self) -> i64 {
+        match self {
+            Timestamp::Wall(start) => start.elapsed().as_nanos() as i64,
+            Timestamp::Cpu(start) => thread_cpu_time()
+                .map(|now| now.saturating_sub(*start).as_nanos() as i64)
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// The CPU time used by the current thread, on platforms which have a clock for it
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd"
+))]
+fn thread_cpu_time() -> Option<Duration> {
+    use nix::time::{ClockId, clock_gettime};
+
+    clock_gettime(ClockId::CLOCK_THREAD_CPUTIME_ID)
+        .ok()
+        .map(Duration::from)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "freebsd"
+)))]
+fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+/// Aggregated statistics of all the elements collapsed into a single row
+struct CollapsedStats {
+    duration_ns: i64,
+    count: i64,
+    max_depth: i64,
+}
+
+/// Aggregated statistics of all the calls to a single command
+#[derive(Default)]
+struct CommandStats {
+    total_ns: i64,
+    self_ns: i64,
+    calls: i64,
 }
 
 /// Whether [`Profiler`] should report duration as [`Value::Duration`]
@@ -52,6 +134,37 @@ pub enum DurationMode {
     Value,
 }
 
+/// Which evaluation steps [`Profiler`] records a row for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilerGranularity {
+    /// Every instruction
+    Instruction,
+    /// Every entered block, without the instructions inside it
+    Block,
+    /// Only the instructions calling a command
+    Call,
+}
+
+/// What a row of the [`Profiler`] report stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilerGroupBy {
+    /// Every recorded element, in order of evaluation
+    Element,
+    /// Every called command, with the time of all its calls added up
+    Command,
+}
+
+/// Which clock [`Profiler`] measures durations with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilerClock {
+    /// Elapsed real time, including time spent waiting, e.g. for IO
+    Wall,
+    /// CPU time of the evaluating thread, without the time it is blocked
+    ///
+    /// Wall time is used instead on platforms without a CPU clock for threads.
+    Cpu,
+}
+
 /// Options for [`Profiler`]
 #[derive(Debug, Clone)]
 pub struct ProfilerOptions {
@@ -63,7 +176,17 @@ pub struct ProfilerOptions {
     pub collect_exprs: bool,
     pub collect_instructions: bool,
     pub collect_lines: bool,
+    pub collapse_recursion: bool,
     pub duration_mode: DurationMode,
+    pub granularity: ProfilerGranularity,
+    /// Only every `sample_rate`th instruction is recorded, with 1 (or 0) recording all of them
+    pub sample_rate: usize,
+    pub group_by: ProfilerGroupBy,
+    /// Calls to commands which are not custom commands are left out of the report
+    pub exclude_builtins: bool,
+    pub clock: ProfilerClock,
+    /// Only the given number of slowest rows are reported, with all rows reported if `None`
+    pub top: Option<usize>,
 }
 
 /// Basic profiler, used in `debug profile`
@@ -73,13 +196,15 @@ pub struct Profiler {
     opts: ProfilerOptions,
     elements: Vec<ElementInfo>,
     element_stack: Vec<ElementId>,
+    /// Instructions considered for recording so far, used for sampling
+    instructions_seen: Cell<usize>,
 }
 
 impl Profiler {
     #[allow(clippy::too_many_arguments)]
     pub fn new(opts: ProfilerOptions, span: Span) -> Self {
         let first = ElementInfo {
-            start: Instant::now(),
+            start: Timestamp::now(opts.clock),
             duration_ns: 0,
             depth: 0,
             element_span: span,
@@ -88,6 +213,7 @@ impl Profiler {
             instruction: opts
                 .collect_instructions
                 .then(|| (0, "<start>".to_string())),
+            decl_id: None,
             children: vec![],
         };
 
@@ -96,6 +222,7 @@ impl Profiler {
             opts,
             elements: vec![first],
             element_stack: vec![ElementId(0)],
+            instructions_seen: Cell::new(0),
         }
     }
 
@@ -110,13 +237,25 @@ impl Profiler {
 }
 
 impl Debugger for Profiler {
+    fn name(&self) -> &str {
+        "profiler"
+    }
+
+    fn status(&self, debugger_span: Span) -> Record {
+        record! {
+            "depth" => Value::int(self.depth, debugger_span),
+            "elements" => Value::int(self.elements.len() as i64, debugger_span),
+        }
+    }
+
     fn activate(&mut self) {
+        let clock = self.opts.clock;
         let Some(root_element) = self.last_element_mut() else {
             eprintln!("Profiler Error: Missing root element.");
             return;
         };
 
-        root_element.start = Instant::now();
+        root_element.start = Timestamp::now(clock);
     }
 
     fn deactivate(&mut self) {
@@ -125,15 +264,55 @@ impl Debugger for Profiler {
             return;
         };
 
-        root_element.duration_ns = root_element.start.elapsed().as_nanos() as i64;
+        root_element.duration_ns = root_element.start.elapsed_ns();
     }
 
-    fn enter_block(&mut self, _engine_state: &EngineState, _block: &Block) {
+    fn enter_block(&mut self, _engine_state: &EngineState, block: &Block) {
         self.depth += 1;
+
+        if self.opts.granularity != ProfilerGranularity::Block || self.depth > self.opts.max_depth {
+            return;
+        }
+
+        let Some(parent_id) = self.last_element_id() else {
+            eprintln!("Profiler Error: Missing parent element ID.");
+            return;
+        };
+
+        let new_id = ElementId(self.elements.len());
+
+        self.elements.push(ElementInfo::new(
+            self.depth,
+            block.span.unwrap_or(Span::unknown()),
+            self.opts.clock,
+        ));
+
+        let Some(parent) = self.elements.get_mut(parent_id.0) else {
+            eprintln!("Profiler Error: Missing parent element.");
+            return;
+        };
+
+        parent.children.push(new_id);
+        self.element_stack.push(new_id);
     }
 
     fn leave_block(&mut self, _engine_state: &EngineState, _block: &Block) {
+        let block_recorded = self.opts.granularity == ProfilerGranularity::Block
+            && self.depth <= self.opts.max_depth;
         self.depth -= 1;
+
+        if !block_recorded {
+            return;
+        }
+
+        let Some(last_element) = self.last_element_mut() else {
+            eprintln!("Profiler Error: Missing last element.");
+            return;
+        };
+
+        last_element.duration_ns = last_element.start.elapsed_ns();
+
+        self.element_stack.pop();
     }
 
     fn enter_element(&mut self, engine_state: &EngineState, element: &PipelineElement) {
@@ -153,7 +332,7 @@ impl Debugger for Profiler {
 
         let new_id = ElementId(self.elements.len());
 
-        let mut new_element = ElementInfo::new(self.depth, element.expr.span);
+        let mut new_element = ElementInfo::new(self.depth, element.expr.span, self.opts.clock);
         new_element.expr = expr_opt;
 
         self.elements.push(new_element);
@@ -194,12 +373,39 @@ impl Debugger for Profiler {
             return;
         };
 
-        last_element.duration_ns = last_element.start.elapsed().as_nanos() as i64;
+        last_element.duration_ns = last_element.start.elapsed_ns();
         last_element.element_output = out_opt;
 
         self.element_stack.pop();
     }
 
+    fn should_enter_instruction(
+        &self,
+        _engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+    ) -> bool {
+        if self.depth > self.opts.max_depth {
+            return false;
+        }
+
+        let enter = match self.opts.granularity {
+            ProfilerGranularity::Instruction => true,
+            ProfilerGranularity::Block => false,
+            ProfilerGranularity::Call => matches!(
+                ir_block.instructions[instruction_index],
+                Instruction::Call { .. }
+            ),
+        };
+        if !enter || self.opts.sample_rate <= 1 {
+            return enter;
+        }
+
+        let seen = self.instructions_seen.get();
+        self.instructions_seen.set(seen.wrapping_add(1));
+        seen % self.opts.sample_rate == 0
+    }
+
     fn enter_instruction(
         &mut self,
         engine_state: &EngineState,
@@ -207,10 +413,6 @@ impl Debugger for Profiler {
         instruction_index: usize,
         _registers: &[PipelineExecutionData],
     ) {
-        if self.depth > self.opts.max_depth {
-            return;
-        }
-
         let Some(parent_id) = self.last_element_id() else {
             eprintln!("Profiler Error: Missing parent element ID.");
             return;
@@ -230,8 +432,11 @@ impl Debugger for Profiler {
 
         let new_id = ElementId(self.elements.len());
 
-        let mut new_element = ElementInfo::new(self.depth, span);
+        let mut new_element = ElementInfo::new(self.depth, span, self.opts.clock);
         new_element.instruction = instruction_opt;
+        if let Instruction::Call { decl_id, .. } = instruction {
+            new_element.decl_id = Some(*decl_id);
+        }
 
         self.elements.push(new_element);
 
@@ -252,10 +457,6 @@ impl Debugger for Profiler {
         registers: &[PipelineExecutionData],
         error: Option<&ShellError>,
     ) {
-        if self.depth > self.opts.max_depth {
-            return;
-        }
-
         let instruction = &ir_block.instructions[instruction_index];
         let span = ir_block.spans[instruction_index];
 
@@ -279,23 +480,47 @@ impl Debugger for Profiler {
             return;
         };
 
-        last_element.duration_ns = last_element.start.elapsed().as_nanos() as i64;
+        last_element.duration_ns = last_element.start.elapsed_ns();
         last_element.element_output = out_opt;
 
         self.element_stack.pop();
     }
 
     fn report(&self, engine_state: &EngineState, profiler_span: Span) -> Result<Value, ShellError> {
-        Ok(Value::list(
-            collect_data(
-                engine_state,
-                self,
-                ElementId(0),
-                ElementId(0),
-                profiler_span,
-            )?,
+        if self.opts.group_by == ProfilerGroupBy::Command {
+            let mut rows = collect_command_data(engine_state, self, profiler_span);
+            if let Some(top) = self.opts.top {
+                // already sorted by total time
+                rows.truncate(top);
+            }
+            return Ok(Value::list(rows, profiler_span));
+        }
+
+        let mut rows = collect_data(
+            engine_state,
+            self,
+            ElementId(0),
+            ElementId(0),
             profiler_span,
-        ))
+        )?;
+        if let Some(top) = self.opts.top {
+            rows.sort_by(|a, b| row_duration(b).total_cmp(&row_duration(a)));
+            rows.truncate(top);
+        }
+        Ok(Value::list(rows, profiler_span))
+    }
+}
+
+/// The duration of a row made by [`element_row`], in the unit of its [`DurationMode`]
+fn row_duration(row: &Value) -> f64 {
+    let duration = row
+        .as_record()
+        .ok()
+        .and_then(|row| row.get("duration_ms").or_else(|| row.get("duration")));
+    match duration {
+        Some(Value::Float { val, .. }) => *val,
+        Some(Value::Duration { val, .. }) => *val as f64,
+        _ => 0.0,
     }
 }
 
@@ -363,7 +588,7 @@ fn expr_to_string(engine_state: &EngineState, expr: &Expr) -> String {
     }
 }
 
-fn format_result(
+pub(super) fn format_result(
     result: Result<&PipelineData, impl Borrow<ShellError>>,
     element_span: Span,
 ) -> Value {
@@ -412,6 +637,272 @@ fn collect_data(
 ) -> Result<Vec<Value>, ShellError> {
     let element = &profiler.elements[element_id.0];
 
+    if is_excluded(engine_state, profiler, element) {
+        // keep the code run by the command, like the closure of `each`, under the parent
+        let mut rows = vec![];
+        for child in &element.children {
+            rows.extend(collect_data(
+                engine_state,
+                profiler,
+                *child,
+                parent_id,
+                profiler_span,
+            )?);
+        }
+        return Ok(rows);
+    }
+
+    if profiler.opts.collapse_recursion && is_recursive(profiler, element_id) {
+        return collect_collapsed_data(
+            engine_state,
+            profiler,
+            element_id,
+            parent_id,
+            profiler_span,
+        );
+    }
+
+    let stats = CollapsedStats {
+        duration_ns: element.duration_ns,
+        count: 1,
+        max_depth: element.depth,
+    };
+    let row = element_row(
+        engine_state,
+        profiler,
+        element_id,
+        parent_id,
+        &stats,
+        profiler_span,
+    )?;
+    let mut rows = vec![Value::record(row, profiler_span)];
+
+    for child in &element.children {
+        let child_rows = collect_data(engine_state, profiler, *child, element_id, profiler_span)?;
+        rows.extend(child_rows);
+    }
+
+    Ok(rows)
+}
+
+/// Test whether an element is left out of the report, because it calls a command which is not a
+/// custom command and [`ProfilerOptions::exclude_builtins`] is set.
+fn is_excluded(engine_state: &EngineState, profiler: &Profiler, element: &ElementInfo) -> bool {
+    profiler.opts.exclude_builtins
+        && element
+            .decl_id
+            .is_some_and(|decl_id| !engine_state.get_decl(decl_id).is_custom())
+}
+
+/// Test whether the code of an element is re-entered while it runs, e.g. by a recursive call.
+fn is_recursive(profiler: &Profiler, element_id: ElementId) -> bool {
+    let element = &profiler.elements[element_id.0];
+    let identity = element.identity();
+
+    let mut stack = element.children.clone();
+    while let Some(id) = stack.pop() {
+        let descendant = &profiler.elements[id.0];
+        if descendant.identity() == identity {
+            return true;
+        }
+        stack.extend(&descendant.children);
+    }
+
+    false
+}
+
+/// Collect the rows of a recursive element, with the elements created from the same code
+/// aggregated into a single row.
+///
+/// Durations of nested occurrences are already part of the duration of their outermost
+/// occurrence, so only outermost occurrences are summed.
+fn collect_collapsed_data(
+    engine_state: &EngineState,
+    profiler: &Profiler,
+    element_id: ElementId,
+    parent_id: ElementId,
+    profiler_span: Span,
+) -> Result<Vec<Value>, ShellError> {
+    let mut order = vec![];
+    let mut aggregated: HashMap<_, (ElementId, ElementId, CollapsedStats)> = HashMap::new();
+
+    // (element, parent, whether the element's code is already running in an ancestor)
+    let mut ancestors = vec![];
+    let mut stack = vec![(element_id, parent_id, false)];
+    while let Some((id, parent, exit)) = stack.pop() {
+        if exit {
+            ancestors.pop();
+            continue;
+        }
+
+        let element = &profiler.elements[id.0];
+        if is_excluded(engine_state, profiler, element) {
+            stack.extend(
+                element
+                    .children
+                    .iter()
+                    .rev()
+                    .map(|child| (*child, parent, false)),
+            );
+            continue;
+        }
+
+        let identity = element.identity();
+        let nested = ancestors.contains(&identity);
+
+        let (_, _, stats) = aggregated.entry(identity).or_insert_with(|| {
+            order.push(identity);
+            (
+                id,
+                parent,
+                CollapsedStats {
+                    duration_ns: 0,
+                    count: 0,
+                    max_depth: element.depth,
+                },
+            )
+        });
+        if !nested {
+            stats.duration_ns += element.duration_ns;
+        }
+        stats.count += 1;
+        stats.max_depth = stats.max_depth.max(element.depth);
+
+        ancestors.push(identity);
+        stack.push((id, parent, true));
+        stack.extend(
+            element
+                .children
+                .iter()
+                .rev()
+                .map(|child| (*child, id, false)),
+        );
+    }
+
+    order
+        .into_iter()
+        .map(|identity| {
+            let (id, parent, stats) = &aggregated[&identity];
+            element_row(engine_state, profiler, *id, *parent, stats, profiler_span)
+                .map(|row| Value::record(row, profiler_span))
+        })
+        .collect()
+}
+
+/// Collect one row per called command, slowest first.
+///
+/// The total time of a command only includes its outermost calls, since the time of recursive
+/// calls is already part of it. The self time excludes the time of the commands it called, except
+/// for excluded built-in commands, whose time is part of the self time of their caller.
+fn collect_command_data(
+    engine_state: &EngineState,
+    profiler: &Profiler,
+    profiler_span: Span,
+) -> Vec<Value> {
+    fn visit(
+        engine_state: &EngineState,
+        profiler: &Profiler,
+        element_id: ElementId,
+        active: &mut Vec<DeclId>,
+        stats: &mut HashMap<DeclId, CommandStats>,
+    ) {
+        let element = &profiler.elements[element_id.0];
+        let Some(decl_id) = counted_call(engine_state, profiler, element) else {
+            for child in &element.children {
+                visit(engine_state, profiler, *child, active, stats);
+            }
+            return;
+        };
+
+        let command = stats.entry(decl_id).or_default();
+        if !active.contains(&decl_id) {
+            command.total_ns += element.duration_ns;
+        }
+        command.self_ns += element.duration_ns - nested_calls_ns(engine_state, profiler, element);
+        command.calls += 1;
+
+        active.push(decl_id);
+        for child in &element.children {
+            visit(engine_state, profiler, *child, active, stats);
+        }
+        active.pop();
+    }
+
+    /// The called command of `element`, unless it is excluded from the report
+    fn counted_call(
+        engine_state: &EngineState,
+        profiler: &Profiler,
+        element: &ElementInfo,
+    ) -> Option<DeclId> {
+        element
+            .decl_id
+            .filter(|_| !is_excluded(engine_state, profiler, element))
+    }
+
+    /// The time of the calls nested in `element`, without the calls nested in those
+    fn nested_calls_ns(
+        engine_state: &EngineState,
+        profiler: &Profiler,
+        element: &ElementInfo,
+    ) -> i64 {
+        element
+            .children
+            .iter()
+            .map(|child| {
+                let child = &profiler.elements[child.0];
+                match counted_call(engine_state, profiler, child) {
+                    Some(_) => child.duration_ns,
+                    None => nested_calls_ns(engine_state, profiler, child),
+                }
+            })
+            .sum()
+    }
+
+    let mut stats = HashMap::new();
+    visit(
+        engine_state,
+        profiler,
+        ElementId(0),
+        &mut vec![],
+        &mut stats,
+    );
+
+    let mut stats: Vec<_> = stats.into_iter().collect();
+    stats.sort_by(|(_, a), (_, b)| b.total_ns.cmp(&a.total_ns));
+
+    stats
+        .into_iter()
+        .map(|(decl_id, stats)| {
+            let mut row = record! {
+                "command" => Value::string(engine_state.get_decl(decl_id).name(), profiler_span),
+            };
+            match profiler.opts.duration_mode {
+                DurationMode::Milliseconds => {
+                    let ms = |ns: i64| Value::float(ns as f64 / 1000.0 / 1000.0, profiler_span);
+                    row.push("total_ms", ms(stats.total_ns));
+                    row.push("self_ms", ms(stats.self_ns));
+                }
+                DurationMode::Value => {
+                    row.push("total", Value::duration(stats.total_ns, profiler_span));
+                    row.push("self", Value::duration(stats.self_ns, profiler_span));
+                }
+            }
+            row.push("calls", Value::int(stats.calls, profiler_span));
+            Value::record(row, profiler_span)
+        })
+        .collect()
+}
+
+fn element_row(
+    engine_state: &EngineState,
+    profiler: &Profiler,
+    element_id: ElementId,
+    parent_id: ElementId,
+    stats: &CollapsedStats,
+    profiler_span: Span,
+) -> Result<Record, ShellError> {
+    let element = &profiler.elements[element_id.0];
+
     let mut row = record! {
         "depth" => Value::int(element.depth, profiler_span),
         "id" => Value::int(element_id.0 as i64, profiler_span),
@@ -487,21 +978,19 @@ fn collect_data(
 
     match profiler.opts.duration_mode {
         DurationMode::Milliseconds => {
-            let val = Value::float(element.duration_ns as f64 / 1000.0 / 1000.0, profiler_span);
+            let val = Value::float(stats.duration_ns as f64 / 1000.0 / 1000.0, profiler_span);
             row.push("duration_ms", val);
         }
         DurationMode::Value => {
-            let val = Value::duration(element.duration_ns, profiler_span);
+            let val = Value::duration(stats.duration_ns, profiler_span);
             row.push("duration", val);
         }
     };
 
-    let mut rows = vec![Value::record(row, profiler_span)];
-
-    for child in &element.children {
-        let child_rows = collect_data(engine_state, profiler, *child, element_id, profiler_span)?;
-        rows.extend(child_rows);
+    if profiler.opts.collapse_recursion {
+        row.push("count", Value::int(stats.count, profiler_span));
+        row.push("max_depth", Value::int(stats.max_depth, profiler_span));
     }
 
-    Ok(rows)
+    Ok(row)
 }
@@ -0,0 +1,163 @@
+//! Nushell execution recorder
+//!
+//! RecordingDebugger implements the Debugger trait and records every evaluated IR instruction. It
+//! is not interactive; the recorded trace is returned by `report()`, e.g. via `debug trace`.
+
+use crate::{
+    PipelineExecutionData, Record, ShellError, Span, Value,
+    ast::Block,
+    debugger::{Debugger, profiler::format_result},
+    engine::EngineState,
+    ir::IrBlock,
+    record,
+};
+
+/// One evaluated instruction
+#[derive(Debug, Clone)]
+struct TraceEntry {
+    depth: i64,
+    pc: usize,
+    instruction: String,
+    span: Span,
+    output: Option<Value>,
+}
+
+/// Debugger recording a trace of every evaluated instruction, used in `debug trace`
+#[derive(Debug, Clone, Default)]
+pub struct RecordingDebugger {
+    depth: i64,
+    collect_values: bool,
+    trace: Vec<TraceEntry>,
+    entry_stack: Vec<usize>,
+}
+
+impl RecordingDebugger {
+    /// Create a new recorder, with `collect_values` also recording the output of each instruction
+    pub fn new(collect_values: bool) -> Self {
+        RecordingDebugger {
+            collect_values,
+            ..Default::default()
+        }
+    }
+}
+
+impl Debugger for RecordingDebugger {
+    fn name(&self) -> &str {
+        "recorder"
+    }
+
+    fn status(&self, debugger_span: Span) -> Record {
+        record! {
+            "depth" => Value::int(self.depth, debugger_span),
+            "instructions" => Value::int(self.trace.len() as i64, debugger_span),
+        }
+    }
+
+    fn enter_block(&mut self, _engine_state: &EngineState, _block: &Block) {
+        self.depth += 1;
+    }
+
+    fn leave_block(&mut self, _engine_state: &EngineState, _block: &Block) {
+        self.depth -= 1;
+    }
+
+    fn enter_instruction(
+        &mut self,
+        engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+        _registers: &[PipelineExecutionData],
+    ) {
+        let instruction = &ir_block.instructions[instruction_index];
+
+        self.entry_stack.push(self.trace.len());
+        self.trace.push(TraceEntry {
+            depth: self.depth,
+            pc: instruction_index,
+            instruction: instruction
+                .display(engine_state, &ir_block.data)
+                .to_string(),
+            span: ir_block.spans[instruction_index],
+            output: None,
+        });
+    }
+
+    fn leave_instruction(
+        &mut self,
+        _engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+        registers: &[PipelineExecutionData],
+        error: Option<&ShellError>,
+    ) {
+        let Some(entry_id) = self.entry_stack.pop() else {
+            eprintln!("Recorder Error: Missing trace entry.");
+            return;
+        };
+
+        if !self.collect_values {
+            return;
+        }
+
+        let instruction = &ir_block.instructions[instruction_index];
+        let span = ir_block.spans[instruction_index];
+
+        let output = error
+            .map(Err)
+            .or_else(|| {
+                instruction
+                    .output_register()
+                    .map(|register| Ok(&registers[register.get() as usize]))
+            })
+            .map(|result| format_result(result.map(|r| &r.body), span));
+
+        if let Some(entry) = self.trace.get_mut(entry_id) {
+            entry.output = output;
+        }
+    }
+
+    fn report(&self, engine_state: &EngineState, debugger_span: Span) -> Result<Value, ShellError> {
+        let rows = self
+            .trace
+            .iter()
+            .enumerate()
+            .map(|(id, entry)| {
+                let source = String::from_utf8_lossy(engine_state.get_span_contents(entry.span));
+                let source = source.trim();
+                let source = match source.split_once('\n') {
+                    Some((first_line, _)) => format!("{} ...", first_line.trim_end()),
+                    None => source.to_string(),
+                };
+
+                let mut row = record! {
+                    "id" => Value::int(id as i64, debugger_span),
+                    "depth" => Value::int(entry.depth, debugger_span),
+                    "source" => Value::string(source, debugger_span),
+                    "pc" => Value::int(entry.pc as i64, debugger_span),
+                    "instruction" => Value::string(entry.instruction.clone(), debugger_span),
+                    "span" => Value::record(
+                        record! {
+                            "start" => Value::int(entry.span.start as i64, debugger_span),
+                            "end" => Value::int(entry.span.end as i64, debugger_span),
+                        },
+                        debugger_span,
+                    ),
+                };
+
+                if self.collect_values {
+                    row.push(
+                        "output",
+                        entry
+                            .output
+                            .clone()
+                            .unwrap_or_else(|| Value::nothing(debugger_span)),
+                    );
+                }
+
+                Value::record(row, debugger_span)
+            })
+            .collect();
+
+        Ok(Value::list(rows, debugger_span))
+    }
+}
@@ -0,0 +1,259 @@
+//! Nushell watchdog
+//!
+//! WatchdogDebugger implements the Debugger trait and interrupts evaluation when a single IR
+//! instruction runs for longer than a configured duration. It is used in `debug watchdog`.
+
+use crate::{
+    BlockId, PipelineExecutionData, Record, ShellError, Signals, Span, Value, debugger::Debugger,
+    engine::EngineState, ir::IrBlock, record,
+};
+use std::{
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The instruction which ran for too long
+#[derive(Debug, Clone)]
+struct SlowStep {
+    pc: usize,
+    span: Span,
+    elapsed: Duration,
+}
+
+/// State shared between the debugger and its watchdog thread
+#[derive(Debug)]
+struct Step {
+    /// When the last instruction was entered, or the debugger was activated
+    started: Instant,
+    pc: usize,
+    span: Span,
+    tripped: Option<SlowStep>,
+    stopped: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    step: Mutex<Option<Step>>,
+    wakeup: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, Option<Step>> {
+        // the state stays valid even if a thread panicked while holding the lock
+        self.step.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Debugger interrupting evaluation when a single instruction takes too long, used in
+/// `debug watchdog`
+///
+/// Each entered instruction records its start time. A separate thread, running while the debugger
+/// is active, triggers `signals` once the time since the last entered instruction exceeds
+/// `max_step`. Instructions running nested instructions, like calls to custom commands, only count
+/// the time until the next nested instruction is entered.
+#[derive(Debug)]
+pub struct WatchdogDebugger {
+    max_step: Duration,
+    signals: Signals,
+    shared: Arc<Shared>,
+}
+
+impl WatchdogDebugger {
+    pub fn new(max_step: Duration, signals: Signals) -> Self {
+        WatchdogDebugger {
+            max_step,
+            signals,
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Whether the watchdog triggered an interrupt
+    pub fn tripped(&self) -> bool {
+        self.shared
+            .lock()
+            .as_ref()
+            .is_some_and(|step| step.tripped.is_some())
+    }
+
+    fn stop(&self) {
+        if let Some(step) = self.shared.lock().as_mut() {
+            step.stopped = true;
+        }
+        self.shared.wakeup.notify_all();
+    }
+}
+
+/// Wait until the current instruction exceeds `max_step`, then trigger `signals`
+fn watch(shared: Arc<Shared>, max_step: Duration, signals: Signals) {
+    let mut guard = shared.lock();
+    loop {
+        let Some(step) = guard.as_mut() else {
+            return;
+        };
+        if step.stopped {
+            return;
+        }
+
+        let elapsed = step.started.elapsed();
+        if elapsed > max_step {
+            step.tripped = Some(SlowStep {
+                pc: step.pc,
+                span: step.span,
+                elapsed,
+            });
+            signals.trigger();
+            return;
+        }
+
+        guard = shared
+            .wakeup
+            .wait_timeout(guard, max_step - elapsed)
+            .map(|(guard, _)| guard)
+            .unwrap_or_else(|err| err.into_inner().0);
+    }
+}
+
+impl Debugger for WatchdogDebugger {
+    fn name(&self) -> &str {
+        "watchdog"
+    }
+
+    fn status(&self, debugger_span: Span) -> Record {
+        record! {
+            "max_step" => Value::duration(self.max_step.as_nanos() as i64, debugger_span),
+            "tripped" => Value::bool(self.tripped(), debugger_span),
+        }
+    }
+
+    fn activate(&mut self) {
+        *self.shared.lock() = Some(Step {
+            started: Instant::now(),
+            pc: 0,
+            span: Span::unknown(),
+            tripped: None,
+            stopped: false,
+        });
+
+        let shared = Arc::clone(&self.shared);
+        let max_step = self.max_step;
+        let signals = self.signals.clone();
+        let spawned = thread::Builder::new()
+            .name("watchdog".into())
+            .spawn(move || watch(shared, max_step, signals));
+        if let Err(err) = spawned {
+            eprintln!("Watchdog Error: could not spawn thread: {err}");
+        }
+    }
+
+    fn deactivate(&mut self) {
+        self.stop();
+    }
+
+    fn enter_instruction(
+        &mut self,
+        _engine_state: &EngineState,
+        ir_block: &IrBlock,
+        instruction_index: usize,
+        _registers: &[PipelineExecutionData],
+    ) {
+        let mut guard = self.shared.lock();
+        let Some(step) = guard.as_mut() else {
+            return;
+        };
+        if step.tripped.is_some() {
+            // keep the instruction which was too slow
+            return;
+        }
+
+        step.started = Instant::now();
+        step.pc = instruction_index;
+        step.span = ir_block.spans[instruction_index];
+    }
+
+    fn report(&self, engine_state: &EngineState, debugger_span: Span) -> Result<Value, ShellError> {
+        let guard = self.shared.lock();
+        let Some(slow) = guard.as_ref().and_then(|step| step.tripped.as_ref()) else {
+            return Ok(Value::nothing(debugger_span));
+        };
+
+        let source = String::from_utf8_lossy(engine_state.get_span_contents(slow.span));
+        let instruction = match find_instruction(engine_state, slow.pc, slow.span) {
+            Some(instruction) => Value::string(instruction, debugger_span),
+            None => Value::nothing(debugger_span),
+        };
+        Ok(Value::record(
+            record! {
+                "source" => Value::string(source.trim(), debugger_span),
+                "pc" => Value::int(slow.pc as i64, debugger_span),
+                "instruction" => instruction,
+                "span" => Value::record(
+                    record! {
+                        "start" => Value::int(slow.span.start as i64, debugger_span),
+                        "end" => Value::int(slow.span.end as i64, debugger_span),
+                    },
+                    debugger_span,
+                ),
+                "elapsed" => Value::duration(
+                    slow.elapsed.as_nanos().try_into().unwrap_or(i64::MAX),
+                    debugger_span,
+                ),
+            },
+            debugger_span,
+        ))
+    }
+}
+
+/// Format the instruction at `pc` of the block whose IR has `span` there
+///
+/// IR blocks don't know their id, so entering an instruction only records its position, and the
+/// block is looked up once a timeout is reported.
+fn find_instruction(engine_state: &EngineState, pc: usize, span: Span) -> Option<String> {
+    (0..engine_state.num_blocks()).find_map(|id| {
+        let ir_block = engine_state.get_block(BlockId::new(id)).ir_block.as_ref()?;
+        (ir_block.spans.get(pc) == Some(&span)).then(|| {
+            ir_block.instructions[pc]
+                .display(engine_state, &ir_block.data)
+                .to_string()
+        })
+    })
+}
+
+impl Drop for WatchdogDebugger {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn idle_watchdog_triggers_signals() {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let mut watchdog =
+            WatchdogDebugger::new(Duration::from_millis(10), Signals::new(interrupt.clone()));
+        watchdog.activate();
+        let start = Instant::now();
+        while !watchdog.tripped() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        watchdog.deactivate();
+        assert!(watchdog.tripped());
+        assert!(interrupt.load(std::sync::atomic::Ordering::Relaxed));
+    }
+
+    #[test]
+    fn stopped_watchdog_does_not_trigger() {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let mut watchdog =
+            WatchdogDebugger::new(Duration::from_millis(10), Signals::new(interrupt.clone()));
+        watchdog.activate();
+        watchdog.deactivate();
+        thread::sleep(Duration::from_millis(30));
+        assert!(!watchdog.tripped());
+        assert!(!interrupt.load(std::sync::atomic::Ordering::Relaxed));
+    }
+}
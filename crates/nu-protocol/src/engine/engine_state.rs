@@ -31,6 +31,9 @@ use std::{
 
 type PoisonDebuggerError<'a> = PoisonError<MutexGuard<'a, Box<dyn Debugger>>>;
 
+/// Callback run by [`EngineState::deactivate_debugger`] with the deactivated debugger
+pub type DebuggerDeactivateHook = dyn Fn(&EngineState, &dyn Debugger) + Send + Sync;
+
 #[cfg(feature = "plugin")]
 use crate::{PluginRegistryFile, PluginRegistryItem, RegisteredPlugin};
 
@@ -119,6 +122,7 @@ pub struct EngineState {
     startup_time: i64,
     is_debugging: IsDebugging,
     pub debugger: Arc<Mutex<Box<dyn Debugger>>>,
+    debugger_deactivate_hook: Option<Arc<DebuggerDeactivateHook>>,
     pub report_log: Arc<Mutex<ReportLog>>,
 
     pub jobs: Arc<Mutex<Jobs>>,
@@ -208,6 +212,7 @@ impl EngineState {
             startup_time: -1,
             is_debugging: IsDebugging::new(false),
             debugger: Arc::new(Mutex::new(Box::new(NoopDebugger))),
+            debugger_deactivate_hook: None,
             report_log: Arc::default(),
             jobs: Arc::new(Mutex::new(Jobs::default())),
             current_job: CurrentJob {
@@ -1066,14 +1071,36 @@ impl EngineState {
         Ok(())
     }
 
+    /// Deactivate the current debugger and return it.
+    ///
+    /// If a hook was set with [`set_debugger_deactivate_hook`](Self::set_debugger_deactivate_hook),
+    /// it is called with the deactivated debugger after the debugger mutex is released. If the
+    /// mutex is poisoned, the error is returned and the hook is not called.
     pub fn deactivate_debugger(&self) -> Result<Box<dyn Debugger>, PoisonDebuggerError<'_>> {
-        let mut locked_debugger = self.debugger.lock()?;
-        locked_debugger.deactivate();
-        let ret = std::mem::replace(&mut *locked_debugger, Box::new(NoopDebugger));
+        let ret = {
+            let mut locked_debugger = self.debugger.lock()?;
+            locked_debugger.deactivate();
+            std::mem::replace(&mut *locked_debugger, Box::new(NoopDebugger))
+        };
         self.is_debugging.0.store(false, Ordering::Relaxed);
+        if let Some(hook) = &self.debugger_deactivate_hook {
+            hook(self, ret.as_ref());
+        }
         Ok(ret)
     }
 
+    /// Set a callback to run every time a debugger is deactivated.
+    ///
+    /// This allows embedders to capture the results of every debugging session, e.g. by calling
+    /// [`Debugger::report`]. The debugger is still returned to the caller of
+    /// [`deactivate_debugger`](Self::deactivate_debugger) afterwards.
+    pub fn set_debugger_deactivate_hook(
+        &mut self,
+        hook: impl Fn(&EngineState, &dyn Debugger) + Send + Sync + 'static,
+    ) {
+        self.debugger_deactivate_hook = Some(Arc::new(hook));
+    }
+
     pub fn is_debugging(&self) -> bool {
         self.is_debugging.0.load(Ordering::Relaxed)
     }
@@ -1144,6 +1171,34 @@ mod engine_state_tests {
 
     use super::*;
 
+    #[test]
+    fn debugger_deactivate_hook_gets_debugger() {
+        use crate::debugger::RecordingDebugger;
+        use std::sync::atomic::AtomicUsize;
+
+        let mut engine_state = EngineState::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        engine_state.set_debugger_deactivate_hook({
+            let calls = Arc::clone(&calls);
+            move |engine_state, debugger| {
+                // the hook must be able to use the engine state's debugger again
+                assert!(!engine_state.is_debugging());
+                assert!(engine_state.debugger.try_lock().is_ok());
+                assert!(format!("{debugger:?}").starts_with("RecordingDebugger"));
+                calls.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+
+        engine_state
+            .activate_debugger(Box::new(RecordingDebugger::new(false)))
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let debugger = engine_state.deactivate_debugger().unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(format!("{debugger:?}").starts_with("RecordingDebugger"));
+    }
+
     #[test]
     fn add_file_gives_id() {
         let engine_state = EngineState::new();
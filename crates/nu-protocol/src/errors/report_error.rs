@@ -8,8 +8,8 @@ use crate::{
     engine::{EngineState, StateWorkingSet},
 };
 use miette::{
-    LabeledSpan, MietteHandlerOpts, NarratableReportHandler, ReportHandler, RgbColors, Severity,
-    SourceCode,
+    JSONReportHandler, LabeledSpan, MietteHandlerOpts, NarratableReportHandler, ReportHandler,
+    RgbColors, Severity, SourceCode,
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -90,10 +90,23 @@ pub fn format_cli_error(
     error: &dyn miette::Diagnostic,
     default_code: Option<&'static str>,
 ) -> String {
-    format!(
-        "Error: {:?}",
-        CliError::new(error, working_set, default_code)
-    )
+    format_report(working_set, "Error", error, default_code)
+}
+
+/// Render a diagnostic in the configured error style, prefixed with `kind` (like `Error`)
+///
+/// JSON reports are not prefixed, so they stay valid JSON.
+fn format_report(
+    working_set: &StateWorkingSet,
+    kind: &str,
+    diagnostic: &dyn miette::Diagnostic,
+    default_code: Option<&'static str>,
+) -> String {
+    let report = CliError::new(diagnostic, working_set, default_code);
+    match working_set.get_config().error_style {
+        ErrorStyle::Json => format!("{report:?}"),
+        ErrorStyle::Plain | ErrorStyle::Fancy => format!("{kind}: {report:?}"),
+    }
 }
 
 pub fn report_shell_error(engine_state: &EngineState, error: &ShellError) {
@@ -140,8 +153,8 @@ fn report_error(
     default_code: &'static str,
 ) {
     eprintln!(
-        "Error: {:?}",
-        CliError::new(error, working_set, Some(default_code))
+        "{}",
+        format_report(working_set, "Error", error, Some(default_code))
     );
     // reset vt processing, aka ansi because illbehaved externals can break it
     #[cfg(windows)]
@@ -156,8 +169,8 @@ fn report_warning(
     default_code: &'static str,
 ) {
     eprintln!(
-        "Warning: {:?}",
-        CliError::new(warning, working_set, Some(default_code))
+        "{}",
+        format_report(working_set, "Warning", warning, Some(default_code))
     );
     // reset vt processing, aka ansi because illbehaved externals can break it
     #[cfg(windows)]
@@ -186,6 +199,7 @@ impl std::fmt::Debug for CliError<'_> {
                     .terminal_links(ansi_support)
                     .build(),
             ),
+            ErrorStyle::Json => Box::new(JSONReportHandler::new()),
         };
 
         // Ignore error to prevent format! panics. This can happen if span points at some
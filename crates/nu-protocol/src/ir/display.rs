@@ -58,101 +58,102 @@ pub struct FmtInstruction<'a> {
 impl fmt::Display for FmtInstruction<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         const WIDTH: usize = 22;
+        let opcode = self.instruction.name();
 
         match self.instruction {
             Instruction::Unreachable => {
-                write!(f, "{:WIDTH$}", "unreachable")
+                write!(f, "{opcode:WIDTH$}")
             }
             Instruction::LoadLiteral { dst, lit } => {
                 let lit = FmtLiteral {
                     literal: lit,
                     data: self.data,
                 };
-                write!(f, "{:WIDTH$} {dst}, {lit}", "load-literal")
+                write!(f, "{opcode:WIDTH$} {dst}, {lit}")
             }
             Instruction::LoadValue { dst, val } => {
                 let val = val.to_debug_string();
-                write!(f, "{:WIDTH$} {dst}, {val}", "load-value")
+                write!(f, "{opcode:WIDTH$} {dst}, {val}")
             }
             Instruction::Move { dst, src } => {
-                write!(f, "{:WIDTH$} {dst}, {src}", "move")
+                write!(f, "{opcode:WIDTH$} {dst}, {src}")
             }
             Instruction::Clone { dst, src } => {
-                write!(f, "{:WIDTH$} {dst}, {src}", "clone")
+                write!(f, "{opcode:WIDTH$} {dst}, {src}")
             }
             Instruction::Collect { src_dst } => {
-                write!(f, "{:WIDTH$} {src_dst}", "collect")
+                write!(f, "{opcode:WIDTH$} {src_dst}")
             }
             Instruction::Span { src_dst } => {
-                write!(f, "{:WIDTH$} {src_dst}", "span")
+                write!(f, "{opcode:WIDTH$} {src_dst}")
             }
             Instruction::Drop { src } => {
-                write!(f, "{:WIDTH$} {src}", "drop")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::Drain { src } => {
-                write!(f, "{:WIDTH$} {src}", "drain")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::DrainIfEnd { src } => {
-                write!(f, "{:WIDTH$} {src}", "drain-if-end")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::LoadVariable { dst, var_id } => {
                 let var = FmtVar::new(self.engine_state, *var_id);
-                write!(f, "{:WIDTH$} {dst}, {var}", "load-variable")
+                write!(f, "{opcode:WIDTH$} {dst}, {var}")
             }
             Instruction::StoreVariable { var_id, src } => {
                 let var = FmtVar::new(self.engine_state, *var_id);
-                write!(f, "{:WIDTH$} {var}, {src}", "store-variable")
+                write!(f, "{opcode:WIDTH$} {var}, {src}")
             }
             Instruction::DropVariable { var_id } => {
                 let var = FmtVar::new(self.engine_state, *var_id);
-                write!(f, "{:WIDTH$} {var}", "drop-variable")
+                write!(f, "{opcode:WIDTH$} {var}")
             }
             Instruction::LoadEnv { dst, key } => {
                 let key = FmtData(self.data, *key);
-                write!(f, "{:WIDTH$} {dst}, {key}", "load-env")
+                write!(f, "{opcode:WIDTH$} {dst}, {key}")
             }
             Instruction::LoadEnvOpt { dst, key } => {
                 let key = FmtData(self.data, *key);
-                write!(f, "{:WIDTH$} {dst}, {key}", "load-env-opt")
+                write!(f, "{opcode:WIDTH$} {dst}, {key}")
             }
             Instruction::StoreEnv { key, src } => {
                 let key = FmtData(self.data, *key);
-                write!(f, "{:WIDTH$} {key}, {src}", "store-env")
+                write!(f, "{opcode:WIDTH$} {key}, {src}")
             }
             Instruction::PushPositional { src } => {
-                write!(f, "{:WIDTH$} {src}", "push-positional")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::AppendRest { src } => {
-                write!(f, "{:WIDTH$} {src}", "append-rest")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::PushFlag { name } => {
                 let name = FmtData(self.data, *name);
-                write!(f, "{:WIDTH$} {name}", "push-flag")
+                write!(f, "{opcode:WIDTH$} {name}")
             }
             Instruction::PushShortFlag { short } => {
                 let short = FmtData(self.data, *short);
-                write!(f, "{:WIDTH$} {short}", "push-short-flag")
+                write!(f, "{opcode:WIDTH$} {short}")
             }
             Instruction::PushNamed { name, src } => {
                 let name = FmtData(self.data, *name);
-                write!(f, "{:WIDTH$} {name}, {src}", "push-named")
+                write!(f, "{opcode:WIDTH$} {name}, {src}")
             }
             Instruction::PushShortNamed { short, src } => {
                 let short = FmtData(self.data, *short);
-                write!(f, "{:WIDTH$} {short}, {src}", "push-short-named")
+                write!(f, "{opcode:WIDTH$} {short}, {src}")
             }
             Instruction::PushParserInfo { name, info } => {
                 let name = FmtData(self.data, *name);
-                write!(f, "{:WIDTH$} {name}, {info:?}", "push-parser-info")
+                write!(f, "{opcode:WIDTH$} {name}, {info:?}")
             }
             Instruction::RedirectOut { mode } => {
-                write!(f, "{:WIDTH$} {mode}", "redirect-out")
+                write!(f, "{opcode:WIDTH$} {mode}")
             }
             Instruction::RedirectErr { mode } => {
-                write!(f, "{:WIDTH$} {mode}", "redirect-err")
+                write!(f, "{opcode:WIDTH$} {mode}")
             }
             Instruction::CheckErrRedirected { src } => {
-                write!(f, "{:WIDTH$} {src}", "check-err-redirected")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::OpenFile {
                 file_num,
@@ -161,70 +162,65 @@ impl fmt::Display for FmtInstruction<'_> {
             } => {
                 write!(
                     f,
-                    "{:WIDTH$} file({file_num}), {path}, append = {append:?}",
-                    "open-file"
+                    "{opcode:WIDTH$} file({file_num}), {path}, append = {append:?}"
                 )
             }
             Instruction::WriteFile { file_num, src } => {
-                write!(f, "{:WIDTH$} file({file_num}), {src}", "write-file")
+                write!(f, "{opcode:WIDTH$} file({file_num}), {src}")
             }
             Instruction::CloseFile { file_num } => {
-                write!(f, "{:WIDTH$} file({file_num})", "close-file")
+                write!(f, "{opcode:WIDTH$} file({file_num})")
             }
             Instruction::Call { decl_id, src_dst } => {
                 let decl = FmtDecl::new(self.engine_state, *decl_id);
-                write!(f, "{:WIDTH$} {decl}, {src_dst}", "call")
+                write!(f, "{opcode:WIDTH$} {decl}, {src_dst}")
             }
             Instruction::StringAppend { src_dst, val } => {
-                write!(f, "{:WIDTH$} {src_dst}, {val}", "string-append")
+                write!(f, "{opcode:WIDTH$} {src_dst}, {val}")
             }
             Instruction::GlobFrom { src_dst, no_expand } => {
                 let no_expand = if *no_expand { "no-expand" } else { "expand" };
-                write!(f, "{:WIDTH$} {src_dst}, {no_expand}", "glob-from",)
+                write!(f, "{opcode:WIDTH$} {src_dst}, {no_expand}",)
             }
             Instruction::ListPush { src_dst, item } => {
-                write!(f, "{:WIDTH$} {src_dst}, {item}", "list-push")
+                write!(f, "{opcode:WIDTH$} {src_dst}, {item}")
             }
             Instruction::ListSpread { src_dst, items } => {
-                write!(f, "{:WIDTH$} {src_dst}, {items}", "list-spread")
+                write!(f, "{opcode:WIDTH$} {src_dst}, {items}")
             }
             Instruction::RecordInsert { src_dst, key, val } => {
-                write!(f, "{:WIDTH$} {src_dst}, {key}, {val}", "record-insert")
+                write!(f, "{opcode:WIDTH$} {src_dst}, {key}, {val}")
             }
             Instruction::RecordSpread { src_dst, items } => {
-                write!(f, "{:WIDTH$} {src_dst}, {items}", "record-spread")
+                write!(f, "{opcode:WIDTH$} {src_dst}, {items}")
             }
             Instruction::Not { src_dst } => {
-                write!(f, "{:WIDTH$} {src_dst}", "not")
+                write!(f, "{opcode:WIDTH$} {src_dst}")
             }
             Instruction::BinaryOp { lhs_dst, op, rhs } => {
-                write!(f, "{:WIDTH$} {lhs_dst}, {op:?}, {rhs}", "binary-op")
+                write!(f, "{opcode:WIDTH$} {lhs_dst}, {op:?}, {rhs}")
             }
             Instruction::FollowCellPath { src_dst, path } => {
-                write!(f, "{:WIDTH$} {src_dst}, {path}", "follow-cell-path")
+                write!(f, "{opcode:WIDTH$} {src_dst}, {path}")
             }
             Instruction::CloneCellPath { dst, src, path } => {
-                write!(f, "{:WIDTH$} {dst}, {src}, {path}", "clone-cell-path")
+                write!(f, "{opcode:WIDTH$} {dst}, {src}, {path}")
             }
             Instruction::UpsertCellPath {
                 src_dst,
                 path,
                 new_value,
             } => {
-                write!(
-                    f,
-                    "{:WIDTH$} {src_dst}, {path}, {new_value}",
-                    "upsert-cell-path"
-                )
+                write!(f, "{opcode:WIDTH$} {src_dst}, {path}, {new_value}")
             }
             Instruction::Jump { index } => {
-                write!(f, "{:WIDTH$} {index}", "jump")
+                write!(f, "{:WIDTH$} {}", opcode, FmtTarget(*index))
             }
             Instruction::BranchIf { cond, index } => {
-                write!(f, "{:WIDTH$} {cond}, {index}", "branch-if")
+                write!(f, "{:WIDTH$} {cond} {}", opcode, FmtTarget(*index))
             }
             Instruction::BranchIfEmpty { src, index } => {
-                write!(f, "{:WIDTH$} {src}, {index}", "branch-if-empty")
+                write!(f, "{:WIDTH$} {src} {}", opcode, FmtTarget(*index))
             }
             Instruction::Match {
                 pattern,
@@ -235,37 +231,56 @@ impl fmt::Display for FmtInstruction<'_> {
                     engine_state: self.engine_state,
                     pattern,
                 };
-                write!(f, "{:WIDTH$} ({pattern}), {src}, {index}", "match")
+                write!(
+                    f,
+                    "{:WIDTH$} ({pattern}), {src} {}",
+                    opcode,
+                    FmtTarget(*index)
+                )
             }
             Instruction::CheckMatchGuard { src } => {
-                write!(f, "{:WIDTH$} {src}", "check-match-guard")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::Iterate {
                 dst,
                 stream,
                 end_index,
             } => {
-                write!(f, "{:WIDTH$} {dst}, {stream}, end {end_index}", "iterate")
+                write!(
+                    f,
+                    "{:WIDTH$} {dst}, {stream}, end {}",
+                    opcode,
+                    FmtTarget(*end_index)
+                )
             }
             Instruction::OnError { index } => {
-                write!(f, "{:WIDTH$} {index}", "on-error")
+                write!(f, "{:WIDTH$} {}", opcode, FmtTarget(*index))
             }
             Instruction::OnErrorInto { index, dst } => {
-                write!(f, "{:WIDTH$} {index}, {dst}", "on-error-into")
+                write!(f, "{:WIDTH$} {}, {dst}", opcode, FmtTarget(*index))
             }
             Instruction::PopErrorHandler => {
-                write!(f, "{:WIDTH$}", "pop-error-handler")
+                write!(f, "{opcode:WIDTH$}")
             }
             Instruction::ReturnEarly { src } => {
-                write!(f, "{:WIDTH$} {src}", "return-early")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
             Instruction::Return { src } => {
-                write!(f, "{:WIDTH$} {src}", "return")
+                write!(f, "{opcode:WIDTH$} {src}")
             }
         }
     }
 }
 
+/// The index of the instruction a branching instruction may continue at
+struct FmtTarget(usize);
+
+impl fmt::Display for FmtTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "-> {}", self.0)
+    }
+}
+
 struct FmtDecl<'a>(DeclId, &'a str);
 
 impl<'a> FmtDecl<'a> {
@@ -6,6 +6,7 @@ use crate::{
 use chrono::{DateTime, FixedOffset};
 use serde::{Deserialize, Serialize};
 use std::{fmt, sync::Arc};
+use strum_macros::{IntoStaticStr, VariantNames};
 
 mod call;
 mod display;
@@ -97,7 +98,10 @@ impl<'de> Deserialize<'de> for IrAstRef {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The opcode names are the variant names in kebab-case, e.g. `drain-if-end` for
+/// [`DrainIfEnd`](Instruction::DrainIfEnd).
+#[derive(Debug, Clone, Serialize, Deserialize, IntoStaticStr, VariantNames)]
+#[strum(serialize_all = "kebab-case")]
 pub enum Instruction {
     /// Unreachable code path (error)
     Unreachable,
@@ -276,6 +280,14 @@ impl Instruction {
         }
     }
 
+    /// The names of all opcodes, as returned by [`name`](Self::name).
+    pub const NAMES: &[&str] = <Self as strum::VariantNames>::VARIANTS;
+
+    /// The name of the instruction's opcode, as shown in the [`display`](Self::display) listing.
+    pub fn name(&self) -> &'static str {
+        self.into()
+    }
+
     /// Get the output register, for instructions that produce some kind of immediate result.
     pub fn output_register(&self) -> Option<RegId> {
         match *self {
@@ -491,3 +503,34 @@ mod serde_arc_u8_array {
         Ok(data.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_kebab_case_variants() {
+        let instructions = [
+            Instruction::Unreachable,
+            Instruction::DrainIfEnd { src: RegId::new(0) },
+            Instruction::OnErrorInto {
+                index: 0,
+                dst: RegId::new(0),
+            },
+        ];
+        let names = instructions
+            .iter()
+            .map(Instruction::name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["unreachable", "drain-if-end", "on-error-into"]);
+        assert!(names.iter().all(|name| Instruction::NAMES.contains(name)));
+    }
+
+    #[test]
+    fn names_are_unique() {
+        let mut names = Instruction::NAMES.to_vec();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), Instruction::NAMES.len());
+    }
+}
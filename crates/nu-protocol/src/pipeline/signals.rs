@@ -1,10 +1,14 @@
-use crate::{ShellError, Span};
+use crate::{Handler, HandlerGuard, Handlers, ShellError, Span};
 use nu_glob::Interruptible;
 use serde::{Deserialize, Serialize};
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
+use std::{
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering},
+    },
+    time::Duration,
 };
+use web_time::Instant;
 
 /// Used to check for signals to suspend or terminate the execution of Nushell code.
 ///
@@ -12,13 +16,29 @@ use std::sync::{
 #[derive(Debug, Clone)]
 pub struct Signals {
     signals: Option<Arc<AtomicBool>>,
+    inner: Option<Arc<Inner>>,
+}
+
+/// The state of a [`Signals`] besides the interrupt flag, shared by all of its clones
+///
+/// It is kept behind a single `Arc`, so that creating and cloning a [`Signals`] stays cheap. The
+/// parts which are only needed by some users are created on first use.
+#[derive(Debug)]
+struct Inner {
+    last_action: AtomicU8,
+    subscribers: OnceLock<Handlers>,
+    debounce: OnceLock<Debounce>,
+    reset_clock: ResetClock,
 }
 
 impl Signals {
     /// A [`Signals`] that is not hooked up to any event/signals source.
     ///
     /// So, this [`Signals`] will never be interrupted.
-    pub const EMPTY: Self = Signals { signals: None };
+    pub const EMPTY: Self = Signals {
+        signals: None,
+        inner: None,
+    };
 
     /// Create a new [`Signals`] with `ctrlc` as the interrupt source.
     ///
@@ -27,9 +47,73 @@ impl Signals {
     pub fn new(ctrlc: Arc<AtomicBool>) -> Self {
         Self {
             signals: Some(ctrlc),
+            inner: Some(Arc::new(Inner {
+                last_action: AtomicU8::new(NO_ACTION),
+                subscribers: OnceLock::new(),
+                debounce: OnceLock::new(),
+                reset_clock: ResetClock::new(),
+            })),
         }
     }
 
+    /// Create a new [`Signals`] together with a [`SignalsTrigger`] to interrupt it.
+    ///
+    /// This is like [`new`](Self::new) with a fresh `AtomicBool`, except that the interrupt
+    /// source is a handle which can only [`trigger`](SignalsTrigger::trigger) and
+    /// [`reset`](SignalsTrigger::reset), e.g. to hand to a thread or to the host of an embedded
+    /// Nushell.
+    pub fn new_shared() -> (Self, SignalsTrigger) {
+        let signals = Self::new(Arc::new(AtomicBool::new(false)));
+        let trigger = SignalsTrigger(signals.clone());
+        (signals, trigger)
+    }
+
+    /// Create a new [`Signals`] which is interrupted once `token` is cancelled.
+    ///
+    /// This lets async hosts interrupt Nushell code with their existing cancellation tokens.
+    /// Cancelling the token [triggers](Self::trigger) the returned [`Signals`], so subscribers are
+    /// notified as well. A [`reset`](Self::reset) does not un-cancel the token, and cancelling the
+    /// token again has no effect.
+    ///
+    /// # Panics
+    ///
+    /// The link is a task spawned with [`tokio::spawn`], so this panics if it is not called from
+    /// within a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn from_token(token: tokio_util::sync::CancellationToken) -> Self {
+        let signals = Self::new(Arc::new(AtomicBool::new(false)));
+        let linked = signals.clone();
+        tokio::spawn(async move {
+            token.cancelled().await;
+            linked.trigger();
+        });
+        signals
+    }
+
+    /// Ignore calls to [`trigger`](Self::trigger) within `window` of the last one that was not
+    /// ignored.
+    ///
+    /// Some terminals send several interrupts for a single ctrl+c, which would otherwise also
+    /// interrupt whatever runs right after the interrupted code. The window applies across a
+    /// [`reset`](Self::reset), and should be kept short (e.g. 50ms), so that a deliberate second
+    /// ctrl+c is still delivered.
+    ///
+    /// The window is shared with all clones of this [`Signals`], and is meant to be set right
+    /// after creating it. Only the first window that is set is used.
+    ///
+    /// There is no force-quit counter for repeated ctrl+c yet. Once there is, it should count the
+    /// triggers accepted by [`trigger`](Self::trigger), so that the duplicates of a single ctrl+c
+    /// are never mistaken for an intentional second one.
+    pub fn with_debounce(self, window: Duration) -> Self {
+        if let Some(inner) = &self.inner {
+            let _ = inner.debounce.set(Debounce {
+                window,
+                last_trigger: Mutex::new(None),
+            });
+        }
+        self
+    }
+
     /// Create a [`Signals`] that is not hooked up to any event/signals source.
     ///
     /// So, the returned [`Signals`] will never be interrupted.
@@ -42,7 +126,7 @@ impl Signals {
 
     /// Returns an `Err` if an interrupt has been triggered.
     ///
-    /// Otherwise, returns `Ok`.
+    /// Otherwise, returns `Ok`. In particular, a [`reset`](Self::reset) never causes an error.
     #[inline]
     pub fn check(&self, span: &Span) -> Result<(), ShellError> {
         #[inline]
@@ -58,11 +142,72 @@ impl Signals {
         }
     }
 
+    /// Returns an `Err` if an interrupt has been triggered after `started_at`.
+    ///
+    /// Like [`check`](Self::check), but an interrupt which was left over from before
+    /// `started_at`, e.g. because a previous command did not [`reset`](Self::reset) it, is
+    /// ignored. See [`interrupted_since`](Self::interrupted_since).
+    pub fn check_fresh(&self, span: &Span, started_at: Instant) -> Result<(), ShellError> {
+        if self.interrupted_since(started_at) {
+            Err(ShellError::Interrupted { span: *span })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns an `Err` if an interrupt has been triggered, reported at the first of `spans`.
+    ///
+    /// Otherwise, returns `Ok`. If `spans` is empty, the error uses [`Span::unknown`].
+    #[inline]
+    pub fn check_batch(&self, spans: &[Span]) -> Result<(), ShellError> {
+        self.check(spans.first().unwrap_or(&Span::unknown()))
+    }
+
     /// Triggers an interrupt.
-    pub fn trigger(&self) {
-        if let Some(signals) = &self.signals {
-            signals.store(true, Ordering::Relaxed);
+    ///
+    /// Subscribers registered with [`subscribe`](Self::subscribe) are notified if this
+    /// [`Signals`] was not already interrupted. Does nothing if the trigger is debounced (see
+    /// [`with_debounce`](Self::with_debounce)).
+    ///
+    /// Returns whether the trigger was accepted, i.e. it was not debounced and this [`Signals`]
+    /// was not already interrupted. Other reactions to the same interrupt, like process-wide
+    /// signal handlers, should only run if it was.
+    pub fn trigger(&self) -> bool {
+        let (Some(signals), Some(inner)) = (&self.signals, &self.inner) else {
+            return false;
+        };
+        if inner.debounce.get().is_some_and(Debounce::is_repeat) {
+            return false;
+        }
+        self.set_last_action(SignalAction::Interrupt);
+        if signals.swap(true, Ordering::Relaxed) {
+            return false;
         }
+        inner.reset_clock.trigger();
+        if let Some(subscribers) = inner.subscribers.get() {
+            subscribers.run(SignalAction::Interrupt);
+        }
+        true
+    }
+
+    /// Registers a closure which will be called when [`trigger`](Self::trigger) interrupts this
+    /// [`Signals`] (or any of its clones).
+    ///
+    /// This allows waiting for an interrupt without polling [`interrupted`](Self::interrupted).
+    /// The closure stays registered until the returned guard is dropped. Returns `None` for
+    /// [`Signals::EMPTY`], since it can never be interrupted.
+    ///
+    /// Note that setting the underlying `AtomicBool` directly does not notify subscribers.
+    pub fn subscribe(&self, handler: Handler) -> Result<Option<HandlerGuard>, ShellError> {
+        self.inner
+            .as_ref()
+            .map(|inner| {
+                inner
+                    .subscribers
+                    .get_or_init(Handlers::new)
+                    .register(handler)
+            })
+            .transpose()
     }
 
     /// Returns whether an interrupt has been triggered.
@@ -73,17 +218,174 @@ impl Signals {
             .is_some_and(|b| b.load(Ordering::Relaxed))
     }
 
+    /// Returns whether an interrupt has been triggered after `started_at`.
+    ///
+    /// An interrupt which is still set from before `started_at` does not count, so that a command
+    /// is not aborted by a leftover interrupt which should have been [`reset`](Self::reset). An
+    /// interrupt set directly through the underlying `AtomicBool` has no known time, and always
+    /// counts.
+    pub fn interrupted_since(&self, started_at: Instant) -> bool {
+        if !self.interrupted() {
+            return false;
+        }
+        match self
+            .inner
+            .as_deref()
+            .and_then(|inner| inner.reset_clock.last_trigger())
+        {
+            Some(triggered_at) => triggered_at >= started_at,
+            None => true,
+        }
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.signals.is_none()
     }
 
     pub fn reset(&self) {
+        self.set_last_action(SignalAction::Reset);
         if let Some(signals) = &self.signals {
             signals.store(false, Ordering::Relaxed);
         }
+        if let Some(inner) = &self.inner {
+            inner.reset_clock.reset();
+        }
+    }
+
+    /// Returns the time elapsed since the last [`reset`](Self::reset), or since this [`Signals`]
+    /// was created if it was never reset.
+    ///
+    /// Commands can use this to limit how long they run, e.g. to at most a few seconds since the
+    /// last prompt. Returns `None` for [`Signals::EMPTY`].
+    pub fn checkpoint(&self) -> Option<Duration> {
+        self.inner
+            .as_deref()
+            .map(|inner| inner.reset_clock.elapsed())
+    }
+
+    /// Returns the [`SignalAction`] that was last applied to this [`Signals`], if any.
+    ///
+    /// An interrupt set directly through the underlying `AtomicBool` is reported as
+    /// [`SignalAction::Interrupt`].
+    pub fn last_action(&self) -> Option<SignalAction> {
+        if self.interrupted() {
+            return Some(SignalAction::Interrupt);
+        }
+        match self.inner.as_deref()?.last_action.load(Ordering::Relaxed) {
+            RESET_ACTION => Some(SignalAction::Reset),
+            // The interrupt flag has been cleared without a reset
+            _ => None,
+        }
+    }
+
+    fn set_last_action(&self, action: SignalAction) {
+        if let Some(inner) = &self.inner {
+            let action = match action {
+                SignalAction::Interrupt => INTERRUPT_ACTION,
+                SignalAction::Reset => RESET_ACTION,
+            };
+            inner.last_action.store(action, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Handle to interrupt the [`Signals`] created by [`Signals::new_shared`].
+///
+/// Unlike a clone of the [`Signals`] itself, this handle cannot be used to check for interrupts,
+/// so it is clear which side is in charge of triggering them.
+#[derive(Debug, Clone)]
+pub struct SignalsTrigger(Signals);
+
+impl SignalsTrigger {
+    /// Triggers an interrupt, see [`Signals::trigger`].
+    pub fn trigger(&self) -> bool {
+        self.0.trigger()
+    }
+
+    /// Resets the interrupt, see [`Signals::reset`].
+    pub fn reset(&self) {
+        self.0.reset();
+    }
+}
+
+/// Shared state of [`Signals::with_debounce`]
+#[derive(Debug)]
+struct Debounce {
+    window: Duration,
+    last_trigger: Mutex<Option<Instant>>,
+}
+
+impl Debounce {
+    /// Returns whether a trigger now is within the window of the last accepted trigger.
+    ///
+    /// Otherwise, the trigger is accepted and starts a new window.
+    fn is_repeat(&self) -> bool {
+        let now = Instant::now();
+        let Ok(mut last_trigger) = self.last_trigger.lock() else {
+            return false;
+        };
+        if last_trigger.is_some_and(|last| now.duration_since(last) < self.window) {
+            return true;
+        }
+        *last_trigger = Some(now);
+        false
+    }
+}
+
+/// Shared state of [`Signals::checkpoint`] and [`Signals::interrupted_since`]
+#[derive(Debug)]
+struct ResetClock {
+    created: Instant,
+    /// Nanoseconds from `created` to the last reset
+    last_reset: AtomicU64,
+    /// Nanoseconds from `created` to the trigger of the current interrupt, or [`NOT_TRIGGERED`]
+    last_trigger: AtomicU64,
+}
+
+/// Marks that no interrupt has been triggered since the last reset
+const NOT_TRIGGERED: u64 = u64::MAX;
+
+impl ResetClock {
+    fn new() -> Self {
+        Self {
+            created: Instant::now(),
+            last_reset: AtomicU64::new(0),
+            last_trigger: AtomicU64::new(NOT_TRIGGERED),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        let since_created = self.created.elapsed().as_nanos();
+        // saturate just below the marker
+        since_created.try_into().unwrap_or(NOT_TRIGGERED - 1)
+    }
+
+    fn reset(&self) {
+        self.last_trigger.store(NOT_TRIGGERED, Ordering::Relaxed);
+        self.last_reset.store(self.now(), Ordering::Relaxed);
+    }
+
+    fn trigger(&self) {
+        self.last_trigger.store(self.now(), Ordering::Relaxed);
+    }
+
+    fn last_trigger(&self) -> Option<Instant> {
+        match self.last_trigger.load(Ordering::Relaxed) {
+            NOT_TRIGGERED => None,
+            nanos => Some(self.created + Duration::from_nanos(nanos)),
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        let last_reset = Duration::from_nanos(self.last_reset.load(Ordering::Relaxed));
+        self.created.elapsed().saturating_sub(last_reset)
     }
 }
 
+const NO_ACTION: u8 = 0;
+const INTERRUPT_ACTION: u8 = 1;
+const RESET_ACTION: u8 = 2;
+
 impl Interruptible for Signals {
     #[inline]
     fn interrupted(&self) -> bool {
@@ -98,3 +400,246 @@ pub enum SignalAction {
     Interrupt,
     Reset,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn counting_handler(count: &Arc<AtomicUsize>) -> Handler {
+        let count = Arc::clone(count);
+        Box::new(move |action| {
+            assert_eq!(action, SignalAction::Interrupt);
+            count.fetch_add(1, Ordering::SeqCst);
+        })
+    }
+
+    #[test]
+    fn subscribers_are_notified_on_trigger() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        let count = Arc::new(AtomicUsize::new(0));
+        let _guard = signals.subscribe(counting_handler(&count)).unwrap();
+
+        signals.clone().trigger();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        // already interrupted, so subscribers are not notified again
+        assert!(!signals.trigger());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        signals.reset();
+        signals.trigger();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn subscribers_are_created_on_first_subscription() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        let has_subscribers = |signals: &Signals| {
+            signals
+                .inner
+                .as_ref()
+                .is_some_and(|inner| inner.subscribers.get().is_some())
+        };
+        signals.trigger();
+        assert!(!has_subscribers(&signals));
+
+        let _guard = signals.clone().subscribe(Box::new(|_| {})).unwrap();
+        assert!(has_subscribers(&signals));
+    }
+
+    #[test]
+    fn dropped_subscription_is_not_notified() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        let count = Arc::new(AtomicUsize::new(0));
+        let guard = signals.subscribe(counting_handler(&count)).unwrap();
+
+        drop(guard);
+        signals.trigger();
+
+        assert!(signals.interrupted());
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn last_action_reports_trigger_and_reset() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        assert_eq!(signals.last_action(), None);
+
+        signals.trigger();
+        assert_eq!(signals.last_action(), Some(SignalAction::Interrupt));
+        assert!(signals.check(&Span::test_data()).is_err());
+
+        signals.reset();
+        assert_eq!(signals.last_action(), Some(SignalAction::Reset));
+        assert!(signals.check(&Span::test_data()).is_ok());
+        assert!(!signals.interrupted());
+    }
+
+    #[test]
+    fn last_action_reports_interrupt_set_through_atomic() {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let signals = Signals::new(interrupt.clone());
+
+        interrupt.store(true, Ordering::Relaxed);
+        assert_eq!(signals.last_action(), Some(SignalAction::Interrupt));
+        assert_eq!(Signals::empty().last_action(), None);
+    }
+
+    #[test]
+    fn check_batch_reports_first_span() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        let spans = [Span::new(1, 2), Span::new(3, 4)];
+        assert!(signals.check_batch(&spans).is_ok());
+
+        signals.trigger();
+        assert!(matches!(
+            signals.check_batch(&spans),
+            Err(ShellError::Interrupted { span }) if span == Span::new(1, 2)
+        ));
+        assert!(matches!(
+            signals.check_batch(&[]),
+            Err(ShellError::Interrupted { span }) if span == Span::unknown()
+        ));
+    }
+
+    #[test]
+    fn debounced_triggers_are_ignored() {
+        let signals =
+            Signals::new(Arc::new(AtomicBool::new(false))).with_debounce(Duration::from_secs(60));
+        let count = Arc::new(AtomicUsize::new(0));
+        let _guard = signals.subscribe(counting_handler(&count)).unwrap();
+
+        assert!(signals.trigger());
+        signals.reset();
+        assert!(!signals.clone().trigger());
+
+        assert!(!signals.interrupted());
+        assert_eq!(signals.last_action(), Some(SignalAction::Reset));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn triggers_after_debounce_window_are_accepted() {
+        let signals =
+            Signals::new(Arc::new(AtomicBool::new(false))).with_debounce(Duration::from_millis(5));
+
+        signals.trigger();
+        signals.reset();
+        std::thread::sleep(Duration::from_millis(20));
+        signals.trigger();
+
+        assert!(signals.interrupted());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn cancelled_token_interrupts_signals() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let token = tokio_util::sync::CancellationToken::new();
+
+        runtime.block_on(async {
+            let signals = Signals::from_token(token.clone());
+            tokio::task::yield_now().await;
+            assert!(!signals.interrupted());
+
+            token.cancel();
+            while !signals.interrupted() {
+                tokio::task::yield_now().await;
+            }
+            assert_eq!(signals.last_action(), Some(SignalAction::Interrupt));
+        });
+    }
+
+    #[test]
+    fn shared_trigger_interrupts_from_another_thread() {
+        let (signals, trigger) = Signals::new_shared();
+        assert!(!signals.interrupted());
+
+        std::thread::spawn(move || trigger.trigger())
+            .join()
+            .expect("trigger thread panicked");
+        assert!(signals.interrupted());
+        assert!(signals.check(&Span::test_data()).is_err());
+    }
+
+    #[test]
+    fn shared_trigger_notifies_subscribers_and_resets() {
+        let (signals, trigger) = Signals::new_shared();
+        let count = Arc::new(AtomicUsize::new(0));
+        let _guard = signals
+            .subscribe(counting_handler(&count))
+            .expect("subscribe");
+
+        trigger.clone().trigger();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        trigger.reset();
+        assert!(!signals.interrupted());
+    }
+
+    #[test]
+    fn checkpoint_measures_from_last_reset() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(signals.checkpoint().unwrap() >= Duration::from_millis(20));
+
+        signals.clone().reset();
+        assert!(signals.checkpoint().unwrap() < Duration::from_millis(20));
+        assert_eq!(Signals::empty().checkpoint(), None);
+    }
+
+    #[test]
+    fn empty_signals_have_no_subscriptions() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let guard = Signals::empty()
+            .subscribe(counting_handler(&count))
+            .unwrap();
+        assert!(guard.is_none());
+    }
+
+    #[test]
+    fn stale_interrupt_is_not_fresh() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        signals.trigger();
+        std::thread::sleep(Duration::from_millis(5));
+
+        // the interrupt was left over from before the command started
+        let started_at = Instant::now();
+        assert!(signals.interrupted());
+        assert!(!signals.interrupted_since(started_at));
+        assert!(signals.check_fresh(&Span::test_data(), started_at).is_ok());
+        assert!(signals.check(&Span::test_data()).is_err());
+    }
+
+    #[test]
+    fn fresh_interrupt_errors() {
+        let signals = Signals::new(Arc::new(AtomicBool::new(false)));
+        let started_at = Instant::now();
+        assert!(!signals.interrupted_since(started_at));
+
+        signals.trigger();
+        assert!(signals.interrupted_since(started_at));
+        assert!(matches!(
+            signals.check_fresh(&Span::test_data(), started_at),
+            Err(ShellError::Interrupted { .. })
+        ));
+
+        // after a reset, the next interrupt is fresh again
+        signals.reset();
+        assert!(!signals.interrupted_since(started_at));
+        signals.trigger();
+        assert!(signals.interrupted_since(started_at));
+    }
+
+    #[test]
+    fn interrupt_without_trigger_time_is_fresh() {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let signals = Signals::new(interrupt.clone());
+        interrupt.store(true, Ordering::Relaxed);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(signals.interrupted_since(Instant::now()));
+    }
+}
@@ -1,6 +1,7 @@
 use nu_engine::{command_prelude::*, get_full_help};
 use nu_parser::{escape_for_script_arg, parse};
 use nu_protocol::{
+    Filesize, TableMode,
     ast::{Expr, Expression},
     engine::StateWorkingSet,
     report_parse_error,
@@ -30,13 +31,16 @@ pub(crate) fn gather_commandline_args() -> (Vec<String>, String, Vec<String>) {
 
         let flag_value = match arg.as_ref() {
             "--commands" | "-c" | "--table-mode" | "-m" | "--error-style" | "-e" | "--execute"
-            | "--config" | "--env-config" | "-I" | "ide-ast" => {
+            | "--config" | "--env-config" | "--profile-startup" | "-I" | "ide-ast" => {
                 args.next().map(|a| escape_quote_string(&a))
             }
             #[cfg(feature = "plugin")]
             "--plugin-config" => args.next().map(|a| escape_quote_string(&a)),
             "--log-level"
             | "--log-target"
+            | "--log-format"
+            | "--log-color"
+            | "--log-rotate"
             | "--log-include"
             | "--log-exclude"
             | "--testbin"
@@ -109,19 +113,26 @@ pub(crate) fn parse_commandline_args(
         let no_std_lib = call.get_named_arg("no-std-lib");
         let config_file = call.get_flag_expr("config");
         let env_file = call.get_flag_expr("env-config");
+        let check_config = call.get_named_arg("check-config");
+        let profile_startup = call.get_flag_expr("profile-startup");
+        let profile_startup_exit = call.get_named_arg("profile-startup-exit");
         let log_level = call.get_flag_expr("log-level");
         let log_target = call.get_flag_expr("log-target");
+        let log_format = call.get_flag_expr("log-format");
+        let log_color = call.get_flag_expr("log-color");
+        let log_rotate: Option<Filesize> = call.get_flag(engine_state, &mut stack, "log-rotate")?;
         let log_include = call.get_flag_expr("log-include");
         let log_exclude = call.get_flag_expr("log-exclude");
+        let log_regex = call.has_flag(engine_state, &mut stack, "log-regex")?;
         let execute = call.get_flag_expr("execute");
         let table_mode: Option<Value> = call.get_flag(engine_state, &mut stack, "table-mode")?;
         let error_style: Option<Value> = call.get_flag(engine_state, &mut stack, "error-style")?;
         let no_newline = call.get_named_arg("no-newline");
+        let execute_strict = call.get_named_arg("execute-strict");
         let experimental_options = call.get_flag_expr("experimental-options");
 
         // ide flags
         let lsp = call.has_flag(engine_state, &mut stack, "lsp")?;
-        let include_path = call.get_flag_expr("include-path");
         let ide_goto_def: Option<Value> =
             call.get_flag(engine_state, &mut stack, "ide-goto-def")?;
         let ide_hover: Option<Value> = call.get_flag(engine_state, &mut stack, "ide-hover")?;
@@ -129,6 +140,10 @@ pub(crate) fn parse_commandline_args(
             call.get_flag(engine_state, &mut stack, "ide-complete")?;
         let ide_check: Option<Value> = call.get_flag(engine_state, &mut stack, "ide-check")?;
         let ide_ast: Option<Spanned<String>> = call.get_named_arg("ide-ast");
+        let ide_symbols: Option<Spanned<String>> = call.get_named_arg("ide-symbols");
+        let ide_format: Option<Spanned<String>> = call.get_named_arg("ide-format");
+        let dump_ir = call.has_flag(engine_state, &mut stack, "dump-ir")?;
+        let ast = call.has_flag(engine_state, &mut stack, "ast")?;
 
         fn extract_contents(
             expression: Option<&Expression>,
@@ -206,15 +221,47 @@ pub(crate) fn parse_commandline_args(
         let plugins = extract_list(plugins, "path", |expr| expr.as_filepath().map(|t| t.0))?;
         let config_file = extract_path(config_file)?;
         let env_file = extract_path(env_file)?;
+        let profile_startup = extract_path(profile_startup)?;
         let log_level = extract_contents(log_level)?;
         let log_target = extract_contents(log_target)?;
+        let log_format = extract_contents(log_format)?;
+        let log_color = extract_contents(log_color)?;
         let log_include = extract_list(log_include, "string", |expr| expr.as_string())?;
         let log_exclude = extract_list(log_exclude, "string", |expr| expr.as_string())?;
         let execute = extract_contents(execute)?;
-        let include_path = extract_contents(include_path)?;
+        // `--include-path` may be given multiple times, collect every occurrence in order
+        let include_path = call
+            .named_iter()
+            .filter(|(name, ..)| name.item == "include-path")
+            .filter_map(|(.., expr)| extract_contents(expr.as_ref()).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+        let include_path = (!include_path.is_empty()).then_some(include_path);
         let experimental_options =
             extract_list(experimental_options, "string", |expr| expr.as_string())?;
 
+        // `--execute` enters the REPL after running, which would draw the prompt right after the
+        // output, so `--no-newline` only applies to `--commands`
+        if let (Some(no_newline), Some(_), None) = (&no_newline, &execute, &commands) {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--no-newline` can only be used with `--commands`, not `--execute`".into(),
+                span: no_newline.span,
+            });
+        }
+
+        if let (Some(execute_strict), None) = (&execute_strict, &execute) {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--execute-strict` can only be used with `--execute`".into(),
+                span: execute_strict.span,
+            });
+        }
+
+        if let (Some(profile_startup_exit), None) = (&profile_startup_exit, &profile_startup) {
+            return Err(ShellError::IncompatibleParametersSingle {
+                msg: "`--profile-startup-exit` can only be used with `--profile-startup`".into(),
+                span: profile_startup_exit.span,
+            });
+        }
+
         let help = call.has_flag(engine_state, &mut stack, "help")?;
 
         if help {
@@ -225,6 +272,14 @@ pub(crate) fn parse_commandline_args(
             std::process::exit(0);
         }
 
+        if table_mode.as_ref().and_then(|mode| mode.as_str().ok()) == Some("list") {
+            let modes = TableMode::NAMES.join("\n");
+            let _ =
+                std::panic::catch_unwind(move || stdout_write_all_and_flush(format!("{modes}\n")));
+
+            std::process::exit(0);
+        }
+
         if call.has_flag(engine_state, &mut stack, "version")? {
             let version = env!("CARGO_PKG_VERSION").to_string();
             let _ = std::panic::catch_unwind(move || {
@@ -249,11 +304,19 @@ pub(crate) fn parse_commandline_args(
             no_std_lib,
             config_file,
             env_file,
+            check_config,
+            profile_startup,
+            profile_startup_exit: profile_startup_exit.is_some(),
             log_level,
             log_target,
+            log_format,
+            log_color,
+            log_rotate,
             log_include,
             log_exclude,
+            log_regex,
             execute,
+            execute_strict: execute_strict.is_some(),
             include_path,
             ide_goto_def,
             ide_hover,
@@ -261,6 +324,10 @@ pub(crate) fn parse_commandline_args(
             lsp,
             ide_check,
             ide_ast,
+            ide_symbols,
+            ide_format,
+            dump_ir,
+            ast,
             table_mode,
             error_style,
             no_newline,
@@ -290,21 +357,33 @@ pub(crate) struct NushellCliArgs {
     pub(crate) no_std_lib: Option<Spanned<String>>,
     pub(crate) config_file: Option<Spanned<String>>,
     pub(crate) env_file: Option<Spanned<String>>,
+    pub(crate) check_config: Option<Spanned<String>>,
+    pub(crate) profile_startup: Option<Spanned<String>>,
+    pub(crate) profile_startup_exit: bool,
     pub(crate) log_level: Option<Spanned<String>>,
     pub(crate) log_target: Option<Spanned<String>>,
+    pub(crate) log_format: Option<Spanned<String>>,
+    pub(crate) log_color: Option<Spanned<String>>,
+    pub(crate) log_rotate: Option<Filesize>,
     pub(crate) log_include: Option<Vec<Spanned<String>>>,
     pub(crate) log_exclude: Option<Vec<Spanned<String>>>,
+    pub(crate) log_regex: bool,
     pub(crate) execute: Option<Spanned<String>>,
+    pub(crate) execute_strict: bool,
     pub(crate) table_mode: Option<Value>,
     pub(crate) error_style: Option<Value>,
     pub(crate) no_newline: Option<Spanned<String>>,
-    pub(crate) include_path: Option<Spanned<String>>,
+    pub(crate) include_path: Option<Vec<Spanned<String>>>,
     pub(crate) lsp: bool,
     pub(crate) ide_goto_def: Option<Value>,
     pub(crate) ide_hover: Option<Value>,
     pub(crate) ide_complete: Option<Value>,
     pub(crate) ide_check: Option<Value>,
     pub(crate) ide_ast: Option<Spanned<String>>,
+    pub(crate) ide_symbols: Option<Spanned<String>>,
+    pub(crate) ide_format: Option<Spanned<String>>,
+    pub(crate) dump_ir: bool,
+    pub(crate) ast: bool,
     pub(crate) experimental_options: Option<Vec<Spanned<String>>>,
 }
 
@@ -328,13 +407,18 @@ impl Command for Nu {
             .named(
                 "execute",
                 SyntaxShape::String,
-                "run the given commands and then enter an interactive shell",
+                "run the given commands and then enter an interactive shell, even if they fail",
                 Some('e'),
             )
+            .switch(
+                "execute-strict",
+                "exit with the error code of the commands of --execute(-e) instead of entering an interactive shell if they fail",
+                None,
+            )
             .named(
                 "include-path",
                 SyntaxShape::String,
-                "set the NU_LIB_DIRS for the given script (delimited by char record_sep ('\x1e'))",
+                "set the NU_LIB_DIRS for the given script (delimited by char record_sep ('\x1e'), can be repeated, searched in the given order)",
                 Some('I'),
             )
             .switch("interactive", "start as an interactive shell", Some('i'))
@@ -342,16 +426,20 @@ impl Command for Nu {
             .named(
                 "table-mode",
                 SyntaxShape::String,
-                "the table mode to use. rounded is default.",
+                "the table mode to use. rounded is default. `list` prints the available table modes",
                 Some('m'),
             )
             .named(
                 "error-style",
                 SyntaxShape::String,
-                "the error style to use (fancy or plain). default: fancy",
+                "the error style to use (fancy, plain or json). default: fancy",
+                None,
+            )
+            .switch(
+                "no-newline",
+                "print the result for --commands(-c) without a newline, cannot be used with --execute(-e)",
                 None,
             )
-            .switch("no-newline", "print the result for --commands(-c) without a newline", None)
             .switch(
                 "no-config-file",
                 "start with no config file and no env file",
@@ -382,6 +470,22 @@ impl Command for Nu {
                 "start with an alternate environment config file",
                 None,
             )
+            .switch(
+                "check-config",
+                "check the config and env files for errors and exit",
+                None,
+            )
+            .named(
+                "profile-startup",
+                SyntaxShape::Filepath,
+                "profile loading the plugin, env and config files and write the report to the given file as nuon",
+                None,
+            )
+            .switch(
+                "profile-startup-exit",
+                "exit after writing the report of --profile-startup",
+                None,
+            )
             .switch(
                "lsp",
                "start nu's language server protocol",
@@ -411,7 +515,27 @@ impl Command for Nu {
                 "run a diagnostic check on the given source and limit number of errors returned to provided number",
                 None,
             )
-            .switch("ide-ast", "generate the ast on the given source", None);
+            .switch("ide-ast", "generate the ast on the given source", None)
+            .switch(
+                "ide-symbols",
+                "list the top-level definitions in the given source",
+                None,
+            )
+            .switch(
+                "ide-format",
+                "print the given source with normalized indentation and without trailing whitespace",
+                None,
+            )
+            .switch(
+                "dump-ir",
+                "print the compiled IR of the given script or commands without running them",
+                None,
+            )
+            .switch(
+                "ast",
+                "print the AST of the given script or commands as a table without running them",
+                None,
+            );
 
         #[cfg(feature = "plugin")]
         {
@@ -443,6 +567,24 @@ impl Command for Nu {
                 "set the target for the log to output. stdout, stderr(default), mixed or file",
                 None,
             )
+            .named(
+                "log-format",
+                SyntaxShape::String,
+                "set the format of the log output. text(default) or json",
+                None,
+            )
+            .named(
+                "log-color",
+                SyntaxShape::String,
+                "color the text log output. auto(default, if logging to a terminal), always or never",
+                None,
+            )
+            .named(
+                "log-rotate",
+                SyntaxShape::Filesize,
+                "rotate the log file of the file log target once it grows beyond the given size",
+                None,
+            )
             .named(
                 "log-include",
                 SyntaxShape::List(Box::new(SyntaxShape::String)),
@@ -455,6 +597,11 @@ impl Command for Nu {
                 "set the Rust module prefixes to exclude from the log output",
                 None,
             )
+            .switch(
+                "log-regex",
+                "match --log-include and --log-exclude as regular expressions instead of module prefixes",
+                None,
+            )
             .switch(
                 "stdin",
                 "redirect standard input to a command (with `-c`) or a script file",
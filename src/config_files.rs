@@ -15,7 +15,7 @@ use std::{
     fs::File,
     io::{Result, Write},
     panic::{AssertUnwindSafe, catch_unwind},
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
@@ -261,6 +261,97 @@ pub(crate) fn setup_config(
     }
 }
 
+/// Evaluate the env and config files without starting a shell, reporting any errors found.
+///
+/// Returns `true` if both files were evaluated successfully. Files which were not specified and
+/// don't exist in the config directory are skipped, and no scaffold files are created.
+pub(crate) fn check_config_files(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    #[cfg(feature = "plugin")] plugin_file: Option<Spanned<String>>,
+    config_file: Option<Spanned<String>>,
+    env_file: Option<Spanned<String>>,
+) -> bool {
+    warn!(
+        "check_config_files() config_file_specified: {:?}, env_file_specified: {:?}",
+        &config_file, &env_file
+    );
+
+    #[cfg(feature = "plugin")]
+    read_plugin_file(engine_state, plugin_file);
+
+    let env_ok = check_config_file(engine_state, stack, env_file, ConfigFileKind::Env);
+    let config_ok = check_config_file(engine_state, stack, config_file, ConfigFileKind::Config);
+    env_ok && config_ok
+}
+
+fn check_config_file(
+    engine_state: &mut EngineState,
+    stack: &mut Stack,
+    config_file: Option<Spanned<String>>,
+    config_kind: ConfigFileKind,
+) -> bool {
+    eval_default_config(engine_state, stack, config_kind);
+
+    let config_path: PathBuf = match config_file {
+        Some(file) => {
+            let cwd = match engine_state.cwd_as_string(Some(stack)) {
+                Ok(cwd) => cwd,
+                Err(e) => {
+                    report_shell_error(engine_state, &e);
+                    return false;
+                }
+            };
+            match canonicalize_with(&file.item, cwd) {
+                Ok(path) if path.is_file() => path,
+                _ => {
+                    let e = ParseError::FileNotFound(file.item, file.span);
+                    report_parse_error(&StateWorkingSet::new(engine_state), &e);
+                    return false;
+                }
+            }
+        }
+        None => match nu_path::nu_config_dir() {
+            Some(mut config_path) => {
+                config_path.push(config_kind.path());
+                if !config_path.is_file() {
+                    return true;
+                }
+                config_path.into()
+            }
+            None => return true,
+        },
+    };
+
+    let contents = match fs::read(&config_path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            eprintln!("Unable to read {}: {err}", config_path.display());
+            return false;
+        }
+    };
+
+    // Set the current active file to the config file.
+    let prev_file = engine_state.file.replace(config_path.clone());
+    let exit_code = eval_source(
+        engine_state,
+        stack,
+        &contents,
+        &config_path.to_string_lossy(),
+        PipelineData::empty(),
+        false,
+    );
+    engine_state.file = prev_file;
+
+    // Merge the environment, so the config file sees the env vars set by the env file
+    if let Err(e) = engine_state.merge_env(stack) {
+        report_shell_error(engine_state, &e);
+        return false;
+    }
+
+    exit_code == 0
+}
+
 pub(crate) fn set_config_path(
     engine_state: &mut EngineState,
     cwd: &Path,
@@ -3,6 +3,7 @@ use nu_cli::NuCompleter;
 use nu_parser::{FlatShape, flatten_block, parse};
 use nu_protocol::{
     DeclId, ShellError, Span, Value, VarId,
+    ast::Expr,
     engine::{EngineState, Stack, StateWorkingSet},
     report_shell_error,
     shell_error::io::{IoError, IoErrorExt, NotFound},
@@ -665,6 +666,197 @@ pub fn ast(engine_state: &mut EngineState, file_path: &str) {
     }
 }
 
+pub fn symbols(engine_state: &mut EngineState, file_path: &str) {
+    let cwd = std::env::current_dir().expect("Could not get current working directory.");
+    engine_state.add_env_var("PWD".into(), Value::test_string(cwd.to_string_lossy()));
+
+    let mut working_set = StateWorkingSet::new(engine_state);
+    let file = std::fs::read(file_path);
+
+    if let Ok(contents) = file {
+        let offset = working_set.next_span_start();
+        let _ = working_set.files.push(file_path.into(), Span::unknown());
+        let parsed_block = parse(&mut working_set, Some(file_path), &contents, false);
+
+        let symbols: Vec<JsonValue> = parsed_block
+            .pipelines
+            .iter()
+            .flat_map(|pipeline| &pipeline.elements)
+            .filter_map(|element| {
+                let mut expr = &element.expr;
+                // definitions can be preceded by attributes
+                if let Expr::AttributeBlock(attribute_block) = &expr.expr {
+                    expr = &*attribute_block.item;
+                }
+                let Expr::Call(call) = &expr.expr else {
+                    return None;
+                };
+
+                let kind = match working_set.get_decl(call.decl_id).name() {
+                    "def" | "export def" => "def",
+                    "extern" | "export extern" => "extern",
+                    "alias" | "export alias" => "alias",
+                    "const" | "export const" => "const",
+                    "module" | "export module" => "module",
+                    _ => return None,
+                };
+                let name_expr = call.positional_nth(0)?;
+                let name = name_expr.as_string().unwrap_or_else(|| {
+                    String::from_utf8_lossy(working_set.get_span_contents(name_expr.span))
+                        .to_string()
+                });
+
+                Some(json!({
+                    "name": name,
+                    "kind": kind,
+                    "span": {
+                        "start": expr.span.start.checked_sub(offset),
+                        "end": expr.span.end.checked_sub(offset),
+                    },
+                }))
+            })
+            .collect();
+
+        println!("{}", JsonValue::Array(symbols));
+    }
+}
+
+pub fn format(file_path: &str) {
+    if let Ok(contents) = std::fs::read_to_string(file_path) {
+        print!("{}", format_source(&contents));
+    }
+}
+
+/// Where [`format_source`] is in the source while scanning it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scan {
+    Code,
+    Comment,
+    DoubleQuote,
+    SingleQuote,
+    Backtick,
+    /// Raw string with the given number of `#`
+    RawString(usize),
+}
+
+impl Scan {
+    fn in_string(self) -> bool {
+        !matches!(self, Scan::Code | Scan::Comment)
+    }
+}
+
+/// Normalize the layout of Nushell source.
+///
+/// This is not a full formatter, it only changes whitespace around lines:
+/// - Lines are indented by four spaces for each line with a `(`, `[` or `{` still open, and lines
+///   starting with a closing delimiter are dedented accordingly.
+/// - Trailing whitespace is trimmed, as are blank lines at the end of the source.
+///
+/// Lines starting inside of a multiline string are kept as they are. If the delimiters don't
+/// balance, e.g. because the source doesn't parse, it is returned unchanged.
+fn format_source(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    // the line each open delimiter is on, so several delimiters on one line indent only once
+    let mut open_lines: Vec<usize> = vec![];
+    let mut scan = Scan::Code;
+
+    for (line_no, line) in source.lines().enumerate() {
+        let starts_in_string = scan.in_string();
+        let trimmed = line.trim_start();
+        let closers = trimmed
+            .chars()
+            .take_while(|c| matches!(c, ')' | ']' | '}'))
+            .count();
+        let kept = open_lines.len().saturating_sub(closers);
+        let mut indent = open_lines[..kept].to_vec();
+        indent.dedup();
+
+        if !scan_line(line, line_no, &mut scan, &mut open_lines) {
+            return source.to_string();
+        }
+
+        if starts_in_string {
+            output.push_str(line);
+        } else if scan.in_string() {
+            // trailing whitespace belongs to the string
+            output.push_str(&"    ".repeat(indent.len()));
+            output.push_str(trimmed);
+        } else if !trimmed.trim_end().is_empty() {
+            output.push_str(&"    ".repeat(indent.len()));
+            output.push_str(trimmed.trim_end());
+        }
+        output.push('\n');
+    }
+
+    if scan.in_string() || !open_lines.is_empty() {
+        return source.to_string();
+    }
+
+    let end = output.trim_end_matches('\n').len();
+    output.truncate(end);
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+/// Track strings, comments and delimiters in a line, returning `false` if a delimiter is closed
+/// which was never opened.
+fn scan_line(line: &str, line_no: usize, scan: &mut Scan, open_lines: &mut Vec<usize>) -> bool {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let at_word_start = i == 0 || bytes[i - 1].is_ascii_whitespace();
+        match *scan {
+            Scan::Code => match byte {
+                b'"' => *scan = Scan::DoubleQuote,
+                b'\'' => *scan = Scan::SingleQuote,
+                b'`' => *scan = Scan::Backtick,
+                b'#' if at_word_start => *scan = Scan::Comment,
+                b'r' if at_word_start || bytes[i - 1] == b'(' => {
+                    let hashes = bytes[i + 1..].iter().take_while(|b| **b == b'#').count();
+                    if hashes > 0 && bytes.get(i + 1 + hashes) == Some(&b'\'') {
+                        *scan = Scan::RawString(hashes);
+                        i += hashes + 1;
+                    }
+                }
+                b'(' | b'[' | b'{' => open_lines.push(line_no),
+                b')' | b']' | b'}' => {
+                    if open_lines.pop().is_none() {
+                        return false;
+                    }
+                }
+                _ => {}
+            },
+            Scan::Comment => break,
+            Scan::DoubleQuote => match byte {
+                b'\\' => i += 1,
+                b'"' => *scan = Scan::Code,
+                _ => {}
+            },
+            Scan::SingleQuote if byte == b'\'' => *scan = Scan::Code,
+            Scan::Backtick if byte == b'`' => *scan = Scan::Code,
+            Scan::RawString(hashes)
+                if byte == b'\''
+                    && bytes[i + 1..].iter().take_while(|b| **b == b'#').count() >= hashes =>
+            {
+                *scan = Scan::Code;
+                i += hashes;
+            }
+            Scan::SingleQuote | Scan::Backtick | Scan::RawString(_) => {}
+        }
+        i += 1;
+    }
+
+    // comments end with the line
+    if *scan == Scan::Comment {
+        *scan = Scan::Code;
+    }
+    true
+}
+
 fn json_merge(a: &mut JsonValue, b: &JsonValue) {
     match (a, b) {
         (JsonValue::Object(a), JsonValue::Object(b)) => {
@@ -683,3 +875,44 @@ fn json_merge(a: &mut JsonValue, b: &JsonValue) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::format_source;
+
+    #[test]
+    fn format_indents_blocks() {
+        let source = "def f [] {\n  if true {\n\t1   \n     } else { 2 }\n}\n\n\n";
+        assert_eq!(
+            format_source(source),
+            "def f [] {\n    if true {\n        1\n    } else { 2 }\n}\n"
+        );
+    }
+
+    #[test]
+    fn format_indents_once_per_line() {
+        let source = "[{\na: 1\n}]";
+        assert_eq!(format_source(source), "[{\n    a: 1\n}]\n");
+    }
+
+    #[test]
+    fn format_ignores_delimiters_in_strings_and_comments() {
+        let source = "let a = \"{\" # (\nlet b = r#'}'#\nlet c = '[' ";
+        assert_eq!(
+            format_source(source),
+            "let a = \"{\" # (\nlet b = r#'}'#\nlet c = '['\n"
+        );
+    }
+
+    #[test]
+    fn format_keeps_multiline_strings() {
+        let source = "do {\n'a  \n  b'\n}";
+        assert_eq!(format_source(source), "do {\n    'a  \n  b'\n}\n");
+    }
+
+    #[test]
+    fn format_unbalanced_source_is_unchanged() {
+        let source = "do {\n  1  \n";
+        assert_eq!(format_source(source), source);
+    }
+}
@@ -1,11 +1,19 @@
-use log::{Level, LevelFilter, SetLoggerError};
+use fancy_regex::Regex;
+use log::{Level, LevelFilter, Log, Metadata, Record};
 use nu_protocol::ShellError;
 use simplelog::{
-    Color, ColorChoice, Config, ConfigBuilder, LevelPadding, TermLogger, TerminalMode, WriteLogger,
+    Color, ColorChoice, ConfigBuilder, LevelPadding, TermLogger, TerminalMode, WriteLogger,
     format_description,
 };
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 
-use std::{fs::File, path::Path, str::FromStr};
+use std::{
+    fs::{self, File},
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::Mutex,
+};
 
 pub enum LogTarget {
     Stdout,
@@ -25,79 +33,403 @@ impl From<&str> for LogTarget {
     }
 }
 
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Whether log lines written to a terminal stream are colored
+#[derive(Clone, Copy)]
+pub enum LogColor {
+    /// Color the log lines if the stream they are written to is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for LogColor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(()),
+        }
+    }
+}
+
+impl LogColor {
+    fn choice(self, mode: TerminalMode) -> ColorChoice {
+        match self {
+            LogColor::Always => ColorChoice::Always,
+            LogColor::Never => ColorChoice::Never,
+            LogColor::Auto => {
+                let is_terminal = match mode {
+                    TerminalMode::Stdout => io::stdout().is_terminal(),
+                    TerminalMode::Stderr => io::stderr().is_terminal(),
+                    TerminalMode::Mixed => io::stdout().is_terminal() && io::stderr().is_terminal(),
+                };
+                // `Auto` still leaves the colors out for `TERM=dumb` and `NO_COLOR`
+                if is_terminal {
+                    ColorChoice::Auto
+                } else {
+                    ColorChoice::Never
+                }
+            }
+        }
+    }
+}
+
 pub fn logger(
     f: impl FnOnce(&mut ConfigBuilder) -> (LevelFilter, LogTarget),
+    filters: Filters,
+    rotate: Option<u64>,
+    color: LogColor,
 ) -> Result<(), ShellError> {
+    let filter = ModuleFilter::new(filters)?;
     let mut builder = ConfigBuilder::new();
     let (level, target) = f(&mut builder);
 
     let config = builder.build();
-    let _ = match target {
-        LogTarget::Stdout => {
-            TermLogger::init(level, config, TerminalMode::Stdout, ColorChoice::Auto)
+    let mut file_failed = false;
+    let inner: Box<dyn Log> = match target {
+        LogTarget::Stdout => TermLogger::new(
+            level,
+            config,
+            TerminalMode::Stdout,
+            color.choice(TerminalMode::Stdout),
+        ),
+        LogTarget::Mixed => TermLogger::new(
+            level,
+            config,
+            TerminalMode::Mixed,
+            color.choice(TerminalMode::Mixed),
+        ),
+        // Use TermLogger instead if WriteLogger is not available
+        LogTarget::File => match LogFile::create(log_file_path(), rotate) {
+            Ok(file) => WriteLogger::new(level, config, file),
+            Err(_) => {
+                file_failed = true;
+                TermLogger::new(
+                    level,
+                    config,
+                    TerminalMode::Stderr,
+                    color.choice(TerminalMode::Stderr),
+                )
+            }
+        },
+        _ => TermLogger::new(
+            level,
+            config,
+            TerminalMode::Stderr,
+            color.choice(TerminalMode::Stderr),
+        ),
+    };
+
+    if log::set_boxed_logger(Box::new(FilteredLogger { inner, filter })).is_ok() {
+        log::set_max_level(level);
+        if file_failed {
+            log::warn!("failed to init WriteLogger, use TermLogger instead");
+        }
+    }
+
+    Ok(())
+}
+
+/// The file used by the `file` log target
+fn log_file_path() -> PathBuf {
+    let pid = std::process::id();
+    let mut path = std::env::temp_dir();
+    path.push(format!("nu-{pid}.log"));
+    path
+}
+
+/// How many rotated log files are kept when using `--log-rotate`
+const LOG_ROTATE_BACKUPS: usize = 3;
+
+/// The log file of the `file` log target
+///
+/// If `max_size` is set, the file is rotated once it has grown to `max_size` bytes. Rotation only
+/// happens at the start of a line, so log records are never split between files. The rotated files
+/// are kept next to the log file with the suffixes `.1` (newest) up to `.3`.
+struct LogFile {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+    max_size: Option<u64>,
+    at_line_start: bool,
+}
+
+impl LogFile {
+    fn create(path: PathBuf, max_size: Option<u64>) -> io::Result<Self> {
+        let file = File::create(&path)?;
+        Ok(LogFile {
+            path,
+            file: Some(file),
+            size: 0,
+            max_size,
+            at_line_start: true,
+        })
+    }
+
+    fn backup_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        // Close the file first, so it can be renamed on all platforms
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        for index in (1..LOG_ROTATE_BACKUPS).rev() {
+            let backup = self.backup_path(index);
+            if backup.exists() {
+                fs::rename(&backup, self.backup_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.backup_path(1))?;
+
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(max_size) = self.max_size
+            && self.at_line_start
+            && self.size >= max_size
+        {
+            self.rotate()?;
+        }
+
+        let mut file = match self.file.take() {
+            Some(file) => file,
+            None => File::create(&self.path)?,
+        };
+        let written = file.write(buf);
+        self.file = Some(file);
+        let written = written?;
+
+        self.size += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
         }
-        LogTarget::Mixed => TermLogger::init(level, config, TerminalMode::Mixed, ColorChoice::Auto),
-        LogTarget::File => {
-            let pid = std::process::id();
-            let mut path = std::env::temp_dir();
-            path.push(format!("nu-{pid}.log"));
+        Ok(written)
+    }
 
-            set_write_logger(level, config, &path)
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
         }
-        _ => TermLogger::init(level, config, TerminalMode::Stderr, ColorChoice::Auto),
+    }
+}
+
+/// Set up a logger that writes every record as a single-line JSON object
+///
+/// Each object has the fields `timestamp` (RFC 3339, UTC), `level`, `module` and `message`.
+pub fn json_logger(
+    level: &str,
+    target: &str,
+    filters: Filters,
+    rotate: Option<u64>,
+) -> Result<(), ShellError> {
+    let level = parse_level(level);
+    let target = LogTarget::from(target);
+
+    let mut file_failed = false;
+    let file = match target {
+        LogTarget::File => match LogFile::create(log_file_path(), rotate) {
+            Ok(file) => Some(Mutex::new(file)),
+            Err(_) => {
+                file_failed = true;
+                None
+            }
+        },
+        _ => None,
+    };
+
+    let logger = JsonLogger {
+        level: level.to_level_filter(),
+        target,
+        file,
+        filter: ModuleFilter::new(filters)?,
     };
 
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level.to_level_filter());
+        if file_failed {
+            log::warn!("failed to create log file, logging to stderr instead");
+        }
+    }
+
     Ok(())
 }
 
-fn set_write_logger(level: LevelFilter, config: Config, path: &Path) -> Result<(), SetLoggerError> {
-    // Use TermLogger instead if WriteLogger is not available
-    if let Ok(file) = File::create(path) {
-        WriteLogger::init(level, config, file)
-    } else {
-        let default_logger =
-            TermLogger::init(level, config, TerminalMode::Stderr, ColorChoice::Auto);
+struct JsonLogger {
+    level: LevelFilter,
+    target: LogTarget,
+    file: Option<Mutex<LogFile>>,
+    filter: ModuleFilter,
+}
 
-        if default_logger.is_ok() {
-            log::warn!("failed to init WriteLogger, use TermLogger instead");
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && self.filter.allows(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
         }
 
-        default_logger
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let line = serde_json::json!({
+            "timestamp": timestamp,
+            "level": record.level().as_str(),
+            "module": record.module_path().unwrap_or(record.target()),
+            "message": record.args().to_string(),
+        });
+
+        // Logging must never bring down the shell, so write errors are ignored
+        let _ = match (&self.target, &self.file) {
+            (LogTarget::File, Some(file)) => match file.lock() {
+                Ok(mut file) => writeln!(file, "{line}"),
+                Err(_) => Ok(()),
+            },
+            (LogTarget::Stdout, _) => writeln!(io::stdout(), "{line}"),
+            (LogTarget::Mixed, _) if record.level() > Level::Error => {
+                writeln!(io::stdout(), "{line}")
+            }
+            _ => writeln!(io::stderr(), "{line}"),
+        };
+    }
+
+    fn flush(&self) {
+        if let Some(Ok(mut file)) = self.file.as_ref().map(Mutex::lock) {
+            let _ = file.flush();
+        }
+        let _ = io::stdout().flush();
     }
 }
 
 pub struct Filters {
     pub include: Option<Vec<String>>,
     pub exclude: Option<Vec<String>>,
+    /// Interpret the filters as regular expressions instead of module prefixes
+    pub regex: bool,
 }
 
-pub fn configure(
-    level: &str,
-    target: &str,
-    filters: Filters,
-    builder: &mut ConfigBuilder,
-) -> (LevelFilter, LogTarget) {
-    let level = match Level::from_str(level) {
-        Ok(level) => level,
-        Err(_) => Level::Warn,
-    };
+/// A single `--log-include` or `--log-exclude` filter
+enum ModulePattern {
+    Prefix(String),
+    Regex(Regex),
+}
 
-    // Add allowed module filter
-    if let Some(include) = filters.include {
-        for filter in include {
-            builder.add_filter_allow(filter);
+impl ModulePattern {
+    fn matches(&self, module: &str) -> bool {
+        match self {
+            ModulePattern::Prefix(prefix) => module.starts_with(prefix),
+            // a regex which fails to run, e.g. by exceeding the backtrack limit, does not match
+            ModulePattern::Regex(regex) => regex.is_match(module).unwrap_or(false),
         }
-    } else {
-        builder.add_filter_allow_str("nu");
     }
+}
+
+/// The compiled [`Filters`], deciding which modules are logged
+///
+/// Without any include filters, only the `nu` modules are logged.
+struct ModuleFilter {
+    include: Vec<ModulePattern>,
+    exclude: Vec<ModulePattern>,
+}
+
+impl ModuleFilter {
+    fn new(filters: Filters) -> Result<Self, ShellError> {
+        let compile = |filters: Option<Vec<String>>, regex: bool| {
+            filters
+                .unwrap_or_default()
+                .into_iter()
+                .map(|filter| {
+                    if !regex {
+                        return Ok(ModulePattern::Prefix(filter));
+                    }
+                    Regex::new(&filter)
+                        .map(ModulePattern::Regex)
+                        .map_err(|err| ShellError::GenericError {
+                            error: "Invalid log filter".into(),
+                            msg: err.to_string(),
+                            span: None,
+                            help: Some(format!("`{filter}` is not a valid regex: {err}")),
+                            inner: vec![],
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let mut include = compile(filters.include, filters.regex)?;
+        if include.is_empty() {
+            include.push(ModulePattern::Prefix("nu".into()));
+        }
+        let exclude = compile(filters.exclude, filters.regex)?;
+        Ok(ModuleFilter { include, exclude })
+    }
+
+    fn allows(&self, module: &str) -> bool {
+        self.include.iter().any(|pattern| pattern.matches(module))
+            && !self.exclude.iter().any(|pattern| pattern.matches(module))
+    }
+}
+
+/// Wraps a logger, only passing on the records of modules allowed by `filter`
+struct FilteredLogger {
+    inner: Box<dyn Log>,
+    filter: ModuleFilter,
+}
 
-    // Add ignored module filter
-    if let Some(exclude) = filters.exclude {
-        for filter in exclude {
-            builder.add_filter_ignore(filter);
+impl Log for FilteredLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.allows(metadata.target()) && self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.filter.allows(record.target()) {
+            self.inner.log(record);
         }
     }
 
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+pub fn configure(
+    level: &str,
+    target: &str,
+    builder: &mut ConfigBuilder,
+) -> (LevelFilter, LogTarget) {
+    let level = parse_level(level);
+
     // Set level padding
     builder.set_level_padding(LevelPadding::Right);
 
@@ -122,6 +454,10 @@ pub fn configure(
     (level.to_level_filter(), log_target)
 }
 
+fn parse_level(level: &str) -> Level {
+    Level::from_str(level).unwrap_or(Level::Warn)
+}
+
 fn set_colored_level(builder: &mut ConfigBuilder, level: Level) {
     let color = match level {
         Level::Trace => Color::Magenta,
@@ -133,3 +469,96 @@ fn set_colored_level(builder: &mut ConfigBuilder, level: Level) {
 
     builder.set_level_color(level, Some(color));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_file_rotates_at_line_start() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("nu.log");
+        let mut file = LogFile::create(path.clone(), Some(10)).expect("failed to create log file");
+
+        // a line is never split between files, even if it goes beyond the size limit
+        write!(file, "first ").unwrap();
+        writeln!(file, "line").unwrap();
+        writeln!(file, "second line").unwrap();
+        for line in ["third line", "fourth line", "fifth line"] {
+            writeln!(file, "{line}").unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: PathBuf| fs::read_to_string(path).expect("failed to read log file");
+        assert_eq!(read(path.clone()), "fifth line\n");
+        assert_eq!(read(file.backup_path(1)), "fourth line\n");
+        assert_eq!(read(file.backup_path(2)), "third line\n");
+        assert_eq!(read(file.backup_path(3)), "second line\n");
+        assert!(!file.backup_path(4).exists());
+    }
+
+    #[test]
+    fn log_file_without_limit_does_not_rotate() {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("nu.log");
+        let mut file = LogFile::create(path.clone(), None).expect("failed to create log file");
+
+        for _ in 0..100 {
+            writeln!(file, "some line").unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 100);
+        assert!(!file.backup_path(1).exists());
+    }
+
+    fn module_filter(include: &[&str], exclude: &[&str], regex: bool) -> ModuleFilter {
+        let to_vec = |filters: &[&str]| {
+            (!filters.is_empty()).then(|| filters.iter().map(|f| f.to_string()).collect())
+        };
+        ModuleFilter::new(Filters {
+            include: to_vec(include),
+            exclude: to_vec(exclude),
+            regex,
+        })
+        .expect("valid filters")
+    }
+
+    #[test]
+    fn module_filter_defaults_to_nu() {
+        let filter = module_filter(&[], &[], false);
+        assert!(filter.allows("nu_command::filters"));
+        assert!(!filter.allows("reedline::engine"));
+    }
+
+    #[test]
+    fn module_filter_matches_prefixes() {
+        let filter = module_filter(&["nu_command", "reedline"], &["nu_command::debug"], false);
+        assert!(filter.allows("nu_command::filters::merge"));
+        assert!(filter.allows("reedline::engine"));
+        assert!(!filter.allows("nu_command::debug::profile"));
+        assert!(!filter.allows("nu_cli::repl"));
+        // entries are literal, even if they look like a regex
+        assert!(!module_filter(&["nu_.*"], &[], false).allows("nu_cli::repl"));
+    }
+
+    #[test]
+    fn module_filter_matches_regexes() {
+        let filter = module_filter(&["^nu_command::filters::.*"], &["::merge(::|$)"], true);
+        assert!(filter.allows("nu_command::filters::sort"));
+        assert!(!filter.allows("nu_command::filters::merge"));
+        assert!(!filter.allows("nu_command::filters::merge::deep"));
+        assert!(filter.allows("nu_command::filters::merger"));
+        assert!(!filter.allows("nu_cli::repl"));
+    }
+
+    #[test]
+    fn module_filter_invalid_regex_errors() {
+        let filters = Filters {
+            include: Some(vec!["nu_(".into()]),
+            exclude: None,
+            regex: true,
+        };
+        assert!(ModuleFilter::new(filters).is_err());
+    }
+}
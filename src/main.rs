@@ -13,7 +13,7 @@ mod test_bins;
 use crate::{
     command::parse_commandline_args,
     config_files::set_config_path,
-    logger::{configure, logger},
+    logger::{LogColor, LogFormat, configure, json_logger, logger},
 };
 use command::gather_commandline_args;
 use log::{Level, trace};
@@ -29,7 +29,7 @@ use nu_protocol::{
 };
 use nu_std::load_standard_library;
 use nu_utils::perf;
-use run::{run_commands, run_file, run_repl};
+use run::{dump_ast, dump_ir, run_commands, run_file, run_repl};
 use signals::ctrlc_protection;
 use std::{borrow::Cow, path::PathBuf, str::FromStr, sync::Arc};
 
@@ -244,6 +244,26 @@ fn main() -> Result<()> {
             .as_ref()
             .map(|target| target.item.clone())
             .unwrap_or_else(|| "stderr".to_string());
+        let format = match parsed_nu_cli_args.log_format.as_ref() {
+            Some(format) => LogFormat::from_str(&format.item).unwrap_or_else(|_| {
+                eprintln!(
+                    "ERROR: unrecognized log format '{}', using default 'text'",
+                    format.item
+                );
+                LogFormat::Text
+            }),
+            None => LogFormat::Text,
+        };
+        let color = match parsed_nu_cli_args.log_color.as_ref() {
+            Some(color) => LogColor::from_str(&color.item).unwrap_or_else(|_| {
+                eprintln!(
+                    "ERROR: unrecognized log color '{}', using default 'auto'",
+                    color.item
+                );
+                LogColor::Auto
+            }),
+            None => LogColor::Auto,
+        };
 
         let make_filters = |filters: &Option<Vec<Spanned<String>>>| {
             filters.as_ref().map(|filters| {
@@ -256,9 +276,23 @@ fn main() -> Result<()> {
         let filters = logger::Filters {
             include: make_filters(&parsed_nu_cli_args.log_include),
             exclude: make_filters(&parsed_nu_cli_args.log_exclude),
+            regex: parsed_nu_cli_args.log_regex,
         };
 
-        logger(|builder| configure(&level, &target, filters, builder))?;
+        let rotate = parsed_nu_cli_args
+            .log_rotate
+            .and_then(|size| u64::try_from(size.get()).ok())
+            .filter(|&size| size > 0);
+
+        match format {
+            LogFormat::Text => logger(
+                |builder| configure(&level, &target, builder),
+                filters,
+                rotate,
+                color,
+            )?,
+            LogFormat::Json => json_logger(&level, &target, filters, rotate)?,
+        }
         // info!("start logging {}:{}:{}", file!(), line!(), column!());
         perf!("start logging", start_time, use_color);
     }
@@ -302,11 +336,15 @@ fn main() -> Result<()> {
 
     start_time = std::time::Instant::now();
     if let Some(include_path) = &parsed_nu_cli_args.include_path {
-        let span = include_path.span;
+        let span = Span::merge_many(include_path.iter().map(|path| path.span));
+        // each occurrence of the flag may itself contain several paths, which are kept in order
         let vals: Vec<_> = include_path
-            .item
-            .split('\x1e') // \x1e is the record separator character (a character that is unlikely to appear in a path)
-            .map(|x| Value::string(x.trim().to_string(), span))
+            .iter()
+            .flat_map(|path| {
+                path.item
+                    .split('\x1e') // \x1e is the record separator character (a character that is unlikely to appear in a path)
+                    .map(|x| Value::string(x.trim().to_string(), path.span))
+            })
             .collect();
 
         let mut working_set = nu_protocol::engine::StateWorkingSet::new(&engine_state);
@@ -368,12 +406,20 @@ fn main() -> Result<()> {
     } else if parsed_nu_cli_args.ide_ast.is_some() {
         ide::ast(&mut engine_state, &script_name);
 
+        return Ok(());
+    } else if parsed_nu_cli_args.ide_symbols.is_some() {
+        ide::symbols(&mut engine_state, &script_name);
+
+        return Ok(());
+    } else if parsed_nu_cli_args.ide_format.is_some() {
+        ide::format(&script_name);
+
         return Ok(());
     }
 
     start_time = std::time::Instant::now();
     if let Some(testbin) = &parsed_nu_cli_args.testbin {
-        let dispatcher = test_bins::new_testbin_dispatcher();
+        let dispatcher = test_bins::new_testbin_dispatcher(engine_state.signals());
         let test_bin = testbin.item.as_str();
         match dispatcher.get(test_bin) {
             Some(test_bin) => test_bin.run(),
@@ -474,6 +520,33 @@ fn main() -> Result<()> {
         }
 
         LanguageServer::initialize_stdio_connection(engine_state)?.serve_requests()?
+    } else if parsed_nu_cli_args.check_config.is_some() {
+        let ok = config_files::check_config_files(
+            &mut engine_state,
+            &mut stack,
+            #[cfg(feature = "plugin")]
+            parsed_nu_cli_args.plugin_file,
+            parsed_nu_cli_args.config_file,
+            parsed_nu_cli_args.env_file,
+        );
+
+        cleanup_exit(0, &engine_state, if ok { 0 } else { 1 });
+    } else if parsed_nu_cli_args.dump_ir {
+        let exit_code = dump_ir(
+            &mut engine_state,
+            parsed_nu_cli_args.commands.as_ref(),
+            &script_name,
+        );
+
+        cleanup_exit(0, &engine_state, exit_code);
+    } else if parsed_nu_cli_args.ast {
+        let exit_code = dump_ast(
+            &engine_state,
+            parsed_nu_cli_args.commands.as_ref(),
+            &script_name,
+        );
+
+        cleanup_exit(0, &engine_state, exit_code);
     } else if let Some(commands) = parsed_nu_cli_args.commands.clone() {
         run_commands(
             &mut engine_state,
@@ -6,12 +6,22 @@ use log::trace;
 #[cfg(feature = "plugin")]
 use nu_cli::read_plugin_file;
 use nu_cli::{EvaluateCommandsOpts, evaluate_commands, evaluate_file, evaluate_repl};
+use nu_engine::exit::cleanup_exit;
+use nu_parser::{flatten_block, parse};
 use nu_protocol::{
-    PipelineData, Spanned,
-    engine::{EngineState, Stack},
-    report_shell_error,
+    IntoPipelineData, PipelineData, ShellError, Span, Spanned, Value,
+    debugger::{
+        DurationMode, Profiler, ProfilerClock, ProfilerGranularity, ProfilerGroupBy,
+        ProfilerOptions,
+    },
+    engine::{EngineState, Stack, StateWorkingSet},
+    record,
+    report_error::report_compile_error,
+    report_parse_error, report_shell_error,
+    shell_error::io::{IoError, IoErrorExt, NotFound},
 };
 use nu_utils::perf;
+use nuon::ToStyle;
 
 pub(crate) fn run_commands(
     engine_state: &mut EngineState,
@@ -32,6 +42,8 @@ pub(crate) fn run_commands(
     // and maybe a custom config file (depending on parsed_nu_cli_args.config_file)
     //
     // if the --no-config-file(-n) flag is passed, do not load plugin, env, or config files
+    start_startup_profile(engine_state, parsed_nu_cli_args.profile_startup.as_ref());
+
     if parsed_nu_cli_args.no_config_file.is_none() {
         #[cfg(feature = "plugin")]
         read_plugin_file(engine_state, parsed_nu_cli_args.plugin_file);
@@ -79,6 +91,12 @@ pub(crate) fn run_commands(
         perf!("read login.nu", start_time, use_color);
     }
 
+    finish_startup_profile(
+        engine_state,
+        parsed_nu_cli_args.profile_startup.as_ref(),
+        parsed_nu_cli_args.profile_startup_exit,
+    );
+
     // Before running commands, set up the startup time
     engine_state.set_startup_time(entire_start_time.elapsed().as_nanos() as i64);
 
@@ -116,6 +134,8 @@ pub(crate) fn run_file(
 ) {
     trace!("run_file");
 
+    start_startup_profile(engine_state, parsed_nu_cli_args.profile_startup.as_ref());
+
     // if the --no-config-file(-n) option is NOT passed, load the plugin file,
     // load the default env file or custom (depending on parsed_nu_cli_args.env_file),
     // and maybe a custom config file (depending on parsed_nu_cli_args.config_file)
@@ -156,6 +176,12 @@ pub(crate) fn run_file(
         perf!("read config.nu", start_time, use_color);
     }
 
+    finish_startup_profile(
+        engine_state,
+        parsed_nu_cli_args.profile_startup.as_ref(),
+        parsed_nu_cli_args.profile_startup_exit,
+    );
+
     // Regenerate the $nu constant to contain the startup time and any other potential updates
     engine_state.generate_nu_constant();
 
@@ -175,6 +201,143 @@ pub(crate) fn run_file(
     }
 }
 
+/// Read the source of the given commands or script file, for `flag` which does not run them.
+///
+/// Reports an error and returns `None` if there is no source or it could not be read.
+fn read_source(
+    engine_state: &EngineState,
+    commands: Option<&Spanned<String>>,
+    script_name: &str,
+    flag: &str,
+) -> Option<(String, Vec<u8>)> {
+    match commands {
+        Some(commands) => Some(("source".to_string(), commands.item.as_bytes().to_vec())),
+        None if script_name.is_empty() => {
+            eprintln!("ERROR: {flag} requires a script file or --commands (-c)");
+            None
+        }
+        None => match std::fs::read(script_name) {
+            Ok(source) => Some((script_name.to_string(), source)),
+            Err(err) => {
+                let err = ShellError::Io(IoError::new_with_additional_context(
+                    err.not_found_as(NotFound::File),
+                    Span::unknown(),
+                    std::path::PathBuf::from(script_name),
+                    "Could not read file",
+                ));
+                report_shell_error(engine_state, &err);
+                None
+            }
+        },
+    }
+}
+
+/// Parse and compile the given commands or script file and print the IR of the resulting block,
+/// without evaluating it.
+///
+/// Returns the exit code to use.
+pub(crate) fn dump_ir(
+    engine_state: &mut EngineState,
+    commands: Option<&Spanned<String>>,
+    script_name: &str,
+) -> i32 {
+    trace!("dump_ir");
+
+    let Some((fname, source)) = read_source(engine_state, commands, script_name, "--dump-ir")
+    else {
+        return 1;
+    };
+
+    let block = {
+        let mut working_set = StateWorkingSet::new(engine_state);
+        let block = parse(&mut working_set, Some(&fname), &source, false);
+
+        if let Some(err) = working_set.parse_errors.first() {
+            report_parse_error(&working_set, err);
+            return 1;
+        }
+        if let Some(err) = working_set.compile_errors.first() {
+            report_compile_error(&working_set, err);
+            return 1;
+        }
+
+        // Merge the definitions, so their names can be displayed
+        if let Err(err) = engine_state.merge_delta(working_set.render()) {
+            report_shell_error(engine_state, &err);
+            return 1;
+        }
+        block
+    };
+
+    match &block.ir_block {
+        Some(ir_block) => {
+            print!("{}", ir_block.display(engine_state));
+            0
+        }
+        None => {
+            eprintln!("ERROR: block is missing compiled representation");
+            1
+        }
+    }
+}
+
+/// Parse the given commands or script file and print their AST as a table, without evaluating
+/// them.
+///
+/// Unlike `--ide-ast`, which prints JSON for editors, the AST is shown like the output of
+/// `ast --flatten`: one row for each piece of the source, with its shape and where it starts and
+/// ends.
+///
+/// Returns the exit code to use.
+pub(crate) fn dump_ast(
+    engine_state: &EngineState,
+    commands: Option<&Spanned<String>>,
+    script_name: &str,
+) -> i32 {
+    trace!("dump_ast");
+
+    let Some((fname, source)) = read_source(engine_state, commands, script_name, "--ast") else {
+        return 1;
+    };
+
+    let ast = {
+        let mut working_set = StateWorkingSet::new(engine_state);
+        let offset = working_set.next_span_start();
+        let block = parse(&mut working_set, Some(&fname), &source, false);
+
+        if let Some(err) = working_set.parse_errors.first() {
+            report_parse_error(&working_set, err);
+            return 1;
+        }
+
+        let span = Span::unknown();
+        flatten_block(&working_set, &block)
+            .into_iter()
+            .map(|(shape_span, shape)| {
+                let content = working_set.get_span_contents(shape_span);
+                Value::record(
+                    record! {
+                        "content" => Value::string(String::from_utf8_lossy(content), span),
+                        "shape" => Value::string(shape.to_string(), span),
+                        "start" => Value::int((shape_span.start - offset) as i64, span),
+                        "end" => Value::int((shape_span.end - offset) as i64, span),
+                    },
+                    span,
+                )
+            })
+            .collect()
+    };
+
+    let ast = Value::list(ast, Span::unknown()).into_pipeline_data();
+    match ast.print_table(engine_state, &mut Stack::new(), false, false) {
+        Ok(()) => 0,
+        Err(err) => {
+            report_shell_error(engine_state, &err);
+            1
+        }
+    }
+}
+
 pub(crate) fn run_repl(
     engine_state: &mut EngineState,
     mut stack: Stack,
@@ -184,6 +347,8 @@ pub(crate) fn run_repl(
     trace!("run_repl");
     let start_time = std::time::Instant::now();
 
+    start_startup_profile(engine_state, parsed_nu_cli_args.profile_startup.as_ref());
+
     if parsed_nu_cli_args.no_config_file.is_none() {
         setup_config(
             engine_state,
@@ -196,6 +361,12 @@ pub(crate) fn run_repl(
         );
     }
 
+    finish_startup_profile(
+        engine_state,
+        parsed_nu_cli_args.profile_startup.as_ref(),
+        parsed_nu_cli_args.profile_startup_exit,
+    );
+
     // Reload use_color from config in case it's different from the default value
     let use_color = engine_state
         .get_config()
@@ -208,6 +379,7 @@ pub(crate) fn run_repl(
         engine_state,
         stack,
         parsed_nu_cli_args.execute,
+        parsed_nu_cli_args.execute_strict,
         parsed_nu_cli_args.no_std_lib,
         entire_start_time,
     );
@@ -215,3 +387,86 @@ pub(crate) fn run_repl(
 
     ret_val
 }
+
+/// Start profiling the startup sequence, if `--profile-startup` was given.
+fn start_startup_profile(engine_state: &EngineState, profile_path: Option<&Spanned<String>>) {
+    if profile_path.is_none() {
+        return;
+    }
+
+    let profiler = Profiler::new(
+        ProfilerOptions {
+            max_depth: 2,
+            collect_spans: false,
+            collect_source: true,
+            collect_expanded_source: false,
+            collect_values: false,
+            collect_exprs: false,
+            collect_instructions: true,
+            collect_lines: true,
+            collapse_recursion: false,
+            duration_mode: DurationMode::Milliseconds,
+            granularity: ProfilerGranularity::Instruction,
+            sample_rate: 1,
+            group_by: ProfilerGroupBy::Element,
+            exclude_builtins: false,
+            clock: ProfilerClock::Wall,
+            top: None,
+        },
+        Span::unknown(),
+    );
+
+    if engine_state.activate_debugger(Box::new(profiler)).is_err() {
+        eprintln!("ERROR: could not start profiling startup, poisoned debugger mutex");
+    }
+}
+
+/// Stop profiling the startup sequence and write the report to the `--profile-startup` path.
+///
+/// Exits afterwards if `exit` is set.
+fn finish_startup_profile(
+    engine_state: &EngineState,
+    profile_path: Option<&Spanned<String>>,
+    exit: bool,
+) {
+    let Some(profile_path) = profile_path else {
+        return;
+    };
+
+    let Ok(profiler) = engine_state.deactivate_debugger() else {
+        eprintln!("ERROR: could not finish profiling startup, poisoned debugger mutex");
+        return;
+    };
+
+    let result = profiler
+        .report(engine_state, profile_path.span)
+        .and_then(|report| {
+            nuon::to_nuon(
+                engine_state,
+                &report,
+                ToStyle::Default,
+                Some(profile_path.span),
+                false,
+            )
+        })
+        .and_then(|report| {
+            std::fs::write(&profile_path.item, report).map_err(|err| {
+                ShellError::Io(IoError::new_with_additional_context(
+                    err,
+                    profile_path.span,
+                    std::path::PathBuf::from(&profile_path.item),
+                    "Could not write startup profile",
+                ))
+            })
+        });
+
+    if let Err(err) = &result {
+        report_shell_error(engine_state, err);
+    }
+
+    if exit {
+        // let scripts notice that the profile they asked for is missing
+        let exit_code = if result.is_ok() { 0 } else { 1 };
+        cleanup_exit((), engine_state, exit_code);
+    }
+}
@@ -1,12 +1,15 @@
 use nu_protocol::{Handlers, SignalAction, Signals, engine::EngineState};
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Duration,
 };
 
+/// Repeated interrupts within this window are treated as a single ctrl+c
+const INTERRUPT_DEBOUNCE: Duration = Duration::from_millis(50);
+
 pub(crate) fn ctrlc_protection(engine_state: &mut EngineState) {
-    let interrupt = Arc::new(AtomicBool::new(false));
-    engine_state.set_signals(Signals::new(interrupt.clone()));
+    let signals = Signals::new(Arc::new(AtomicBool::new(false))).with_debounce(INTERRUPT_DEBOUNCE);
+    engine_state.set_signals(signals.clone());
 
     let signal_handlers = Handlers::new();
 
@@ -27,8 +30,10 @@ pub(crate) fn ctrlc_protection(engine_state: &mut EngineState) {
     engine_state.signal_handlers = Some(signal_handlers.clone());
 
     ctrlc::set_handler(move || {
-        interrupt.store(true, Ordering::Relaxed);
-        signal_handlers.run(SignalAction::Interrupt);
+        // a debounced or repeated interrupt is part of the ctrl+c which was already handled
+        if signals.trigger() {
+            signal_handlers.run(SignalAction::Interrupt);
+        }
     })
     .expect("Error setting Ctrl-C handler");
 }
@@ -2,7 +2,7 @@ use nu_cmd_base::hook::{eval_env_change_hook, eval_hooks};
 use nu_engine::eval_block;
 use nu_parser::parse;
 use nu_protocol::{
-    PipelineData, ShellError, Value,
+    PipelineData, ShellError, Signals, Value,
     debugger::WithoutDebug,
     engine::{EngineState, Stack, StateWorkingSet},
     report_parse_error, report_shell_error,
@@ -35,6 +35,10 @@ pub struct Repeater;
 pub struct RepeatBytes;
 pub struct NuRepl;
 pub struct InputBytesLength;
+pub struct Interruptible {
+    /// The signals triggered by the ctrl+c handler of the process
+    pub signals: Signals,
+}
 
 impl TestBin for EchoEnv {
     fn help(&self) -> &'static str {
@@ -311,6 +315,20 @@ impl TestBin for InputBytesLength {
     }
 }
 
+impl TestBin for Interruptible {
+    fn help(&self) -> &'static str {
+        "Runs a loop for <s> seconds (default 10), printing ready when it starts, then interrupted if it was stopped by ctrl+c or completed otherwise(e.g: nu --testbin interruptible 5)"
+    }
+
+    fn run(&self) {
+        let seconds: u64 = args()
+            .get(1)
+            .map(|arg| arg.parse().expect("given seconds should be a number"))
+            .unwrap_or(10);
+        interruptible(self.signals.clone(), seconds);
+    }
+}
+
 /// Echo's value of env keys from args
 /// Example: nu --testbin env_echo FOO BAR
 /// If it it's not present echo's nothing
@@ -458,6 +476,42 @@ pub fn nu_repl() {
     outcome_ok(last_output)
 }
 
+/// Runs a nushell loop for `seconds`, reporting whether it was interrupted through `signals`
+/// Example: nu --testbin interruptible 5
+pub fn interruptible(signals: Signals, seconds: u64) {
+    let mut engine_state = get_engine_state();
+    engine_state.set_signals(signals);
+
+    let source = format!("for _ in 0..<({seconds} * 100) {{ sleep 10ms }}");
+    let (block, delta) = {
+        let mut working_set = StateWorkingSet::new(&engine_state);
+        let block = parse(&mut working_set, None, source.as_bytes(), false);
+
+        if let Some(err) = working_set.parse_errors.first() {
+            report_parse_error(&working_set, err);
+            std::process::exit(1);
+        }
+        (block, working_set.render())
+    };
+
+    if let Err(err) = engine_state.merge_delta(delta) {
+        outcome_err(&engine_state, &err);
+    }
+
+    println!("ready");
+    let _ = io::stdout().flush();
+
+    let mut stack = Stack::new();
+    let result =
+        eval_block::<WithoutDebug>(&engine_state, &mut stack, &block, PipelineData::empty())
+            .and_then(|data| data.body.drain());
+    match result {
+        Ok(_) => outcome_ok("completed".into()),
+        Err(ShellError::Interrupted { .. }) => outcome_ok("interrupted".into()),
+        Err(err) => outcome_err(&engine_state, &err),
+    }
+}
+
 fn did_chop_arguments() -> bool {
     let args: Vec<String> = args();
 
@@ -498,7 +552,9 @@ pub fn show_help(dispatcher: &std::collections::HashMap<String, Box<dyn TestBin>
 }
 
 /// Create a new testbin dispatcher, which is useful to guide the testbin to run.
-pub fn new_testbin_dispatcher() -> HashMap<String, Box<dyn TestBin>> {
+///
+/// `signals` are the signals of the process, for testbins which can be interrupted.
+pub fn new_testbin_dispatcher(signals: &Signals) -> HashMap<String, Box<dyn TestBin>> {
     let mut dispatcher: HashMap<String, Box<dyn TestBin>> = HashMap::new();
     dispatcher.insert("echo_env".to_string(), Box::new(EchoEnv));
     dispatcher.insert("echo_env_stderr".to_string(), Box::new(EchoEnvStderr));
@@ -519,5 +575,11 @@ pub fn new_testbin_dispatcher() -> HashMap<String, Box<dyn TestBin>> {
     dispatcher.insert("repeat_bytes".to_string(), Box::new(RepeatBytes));
     dispatcher.insert("nu_repl".to_string(), Box::new(NuRepl));
     dispatcher.insert("input_bytes_length".to_string(), Box::new(InputBytesLength));
+    dispatcher.insert(
+        "interruptible".to_string(),
+        Box::new(Interruptible {
+            signals: signals.clone(),
+        }),
+    );
     dispatcher
 }
@@ -8,3 +8,35 @@ fn parser_recovers() -> TestResult {
         "\"typename\":\"string\"",
     )
 }
+
+#[test]
+fn ide_symbols_lists_defs() -> TestResult {
+    test_ide_contains(
+        "def foo [] { 1 }\nexport def \"foo bar\" [] { 2 }\n",
+        &["--ide-symbols"],
+        "\"name\":\"foo bar\"",
+    )
+}
+
+#[test]
+fn ide_symbols_lists_aliases_and_consts() -> TestResult {
+    test_ide_contains(
+        "alias ll = ls -l\nconst answer = 42\n",
+        &["--ide-symbols"],
+        "\"kind\":\"const\"",
+    )?;
+    test_ide_contains(
+        "alias ll = ls -l\nconst answer = 42\n",
+        &["--ide-symbols"],
+        "\"kind\":\"alias\"",
+    )
+}
+
+#[test]
+fn ide_format_normalizes_indentation() -> TestResult {
+    test_ide_contains(
+        "def foo [] {\n  if true {   \n1\n}\n}\n\n",
+        &["--ide-format"],
+        "def foo [] {\n    if true {\n        1\n    }\n}\n",
+    )
+}
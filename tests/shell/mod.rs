@@ -282,6 +282,490 @@ fn run_with_no_newline() {
     assert!(child_output.stderr.is_empty());
 }
 
+#[test]
+fn run_with_no_newline_and_execute_errors() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--no-newline", "-e", "\"hello world\""])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(!child_output.status.success());
+    assert!(child_output.stdout.is_empty());
+    assert!(
+        String::from_utf8_lossy(&child_output.stderr)
+            .contains("`--no-newline` can only be used with `--commands`")
+    );
+}
+
+#[test]
+fn run_with_no_newline_prefers_commands_over_execute() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--no-newline", "-e", "\"ignored\"", "-c", "\"hello\""])
+        .output()
+        .expect("failed to run nu");
+
+    assert_eq!("hello", String::from_utf8_lossy(&child_output.stdout));
+}
+
+#[test]
+fn run_with_failing_execute_still_enters_repl() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "-e", "error make {msg: boom}"])
+        .output()
+        .expect("failed to run nu");
+
+    let stderr = String::from_utf8_lossy(&child_output.stderr);
+    assert!(stderr.contains("boom"));
+    // the REPL is started after the error, but there is no terminal to run it in
+    assert!(stderr.contains("STDIN is not a TTY"));
+}
+
+#[test]
+fn run_with_execute_strict_exits_on_failure() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--execute-strict", "-e", "error make {msg: boom}"])
+        .output()
+        .expect("failed to run nu");
+
+    let stderr = String::from_utf8_lossy(&child_output.stderr);
+    assert_eq!(child_output.status.code(), Some(1));
+    assert!(stderr.contains("boom"));
+    assert!(!stderr.contains("STDIN is not a TTY"));
+}
+
+#[test]
+fn run_with_execute_strict_enters_repl_on_success() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--execute-strict", "-e", "print hello"])
+        .output()
+        .expect("failed to run nu");
+
+    assert_eq!("hello\n", String::from_utf8_lossy(&child_output.stdout));
+    assert!(String::from_utf8_lossy(&child_output.stderr).contains("STDIN is not a TTY"));
+}
+
+#[test]
+fn run_with_execute_strict_without_execute_errors() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--execute-strict", "-c", "print hello"])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(!child_output.status.success());
+    assert!(child_output.stdout.is_empty());
+    assert!(
+        String::from_utf8_lossy(&child_output.stderr)
+            .contains("`--execute-strict` can only be used with `--execute`")
+    );
+}
+
+#[test]
+fn run_with_json_error_style() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args([
+            "-n",
+            "--error-style",
+            "json",
+            "-c",
+            "error make {msg: boom, label: {text: here, span: (metadata 1).span}, help: 'try again'}",
+        ])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(!child_output.status.success());
+    let stderr = String::from_utf8_lossy(&child_output.stderr);
+    let error: serde_json::Value = serde_json::from_str(stderr.trim()).expect("valid json");
+    assert_eq!(error["message"], "boom");
+    assert_eq!(error["code"], "nu::shell::error");
+    assert_eq!(error["help"], "try again");
+    assert_eq!(error["labels"][0]["label"], "here");
+    assert_eq!(error["labels"][0]["span"]["length"], 1);
+}
+
+#[test]
+fn run_with_invalid_error_style() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--error-style", "xml", "-c", "1"])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(!child_output.status.success());
+    assert!(String::from_utf8_lossy(&child_output.stderr).contains("'fancy', 'plain' or 'json'"));
+}
+
+#[test]
+fn run_with_json_log_format() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args([
+            "-n",
+            "--log-level",
+            "trace",
+            "--log-format",
+            "json",
+            "-c",
+            "\"hello world\"",
+        ])
+        .output()
+        .expect("failed to run nu");
+
+    assert_eq!(
+        "hello world\n",
+        String::from_utf8_lossy(&child_output.stdout)
+    );
+
+    let stderr = String::from_utf8_lossy(&child_output.stderr);
+    let entries = stderr
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("valid json"))
+        .collect::<Vec<_>>();
+
+    assert!(!entries.is_empty());
+    assert!(entries.iter().all(|entry| {
+        ["timestamp", "level", "module", "message"]
+            .iter()
+            .all(|key| entry.get(key).is_some())
+    }));
+    assert!(
+        entries
+            .iter()
+            .any(|entry| entry["message"] == "run_commands")
+    );
+}
+
+fn run_with_log_color(color: &str) -> String {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args([
+            "-n",
+            "--log-level",
+            "trace",
+            "--log-color",
+            color,
+            "-c",
+            "\"hello world\"",
+        ])
+        .env_remove("NO_COLOR")
+        .env("TERM", "xterm")
+        .output()
+        .expect("failed to run nu");
+
+    String::from_utf8_lossy(&child_output.stderr).into_owned()
+}
+
+#[test]
+fn run_with_log_color_always() {
+    let stderr = run_with_log_color("always");
+    assert!(stderr.contains("run_commands"));
+    assert!(stderr.contains('\x1b'));
+}
+
+#[test]
+fn run_with_log_color_never() {
+    let stderr = run_with_log_color("never");
+    assert!(stderr.contains("run_commands"));
+    assert!(!stderr.contains('\x1b'));
+}
+
+#[test]
+fn run_with_log_color_auto_without_terminal() {
+    // the output of the child is captured, so stderr is not a terminal
+    let stderr = run_with_log_color("auto");
+    assert!(stderr.contains("run_commands"));
+    assert!(!stderr.contains('\x1b'));
+}
+
+#[test]
+fn run_with_log_regex_filters_modules() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args([
+            "-n",
+            "--log-level",
+            "trace",
+            "--log-format",
+            "json",
+            "--log-regex",
+            "--log-include",
+            "[^nu_cli::]",
+            "-c",
+            "\"hello world\"",
+        ])
+        .output()
+        .expect("failed to run nu");
+
+    let stderr = String::from_utf8_lossy(&child_output.stderr);
+    let modules = stderr
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("valid json"))
+        .map(|entry| entry["module"].as_str().unwrap_or_default().to_string())
+        .collect::<Vec<_>>();
+
+    assert!(!modules.is_empty());
+    assert!(modules.iter().all(|module| module.starts_with("nu_cli::")));
+}
+
+#[test]
+fn run_with_invalid_log_regex_errors() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args([
+            "-n",
+            "--log-level",
+            "trace",
+            "--log-regex",
+            "--log-include",
+            "[\"nu_(\"]",
+            "-c",
+            "\"hello world\"",
+        ])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(!child_output.status.success());
+    assert!(String::from_utf8_lossy(&child_output.stderr).contains("Invalid log filter"));
+}
+
+#[test]
+fn check_config_succeeds_for_valid_config() {
+    Playground::setup("check_config_valid", |dirs, sandbox| {
+        sandbox.with_files(&[
+            FileWithContent("env.nu", "$env.FOO = 'foo'"),
+            FileWithContent("config.nu", "$env.config.show_banner = ($env.FOO == 'foo')"),
+        ]);
+
+        let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+            .current_dir(dirs.test())
+            .args([
+                "--env-config",
+                "env.nu",
+                "--config",
+                "config.nu",
+                "--check-config",
+            ])
+            .output()
+            .expect("failed to run nu");
+
+        assert!(child_output.status.success());
+        assert!(child_output.stdout.is_empty());
+    })
+}
+
+#[test]
+fn check_config_fails_for_invalid_config() {
+    Playground::setup("check_config_invalid", |dirs, sandbox| {
+        sandbox.with_files(&[
+            FileWithContent("env.nu", "$env.FOO = 'foo'"),
+            FileWithContent("config.nu", "let x = ("),
+        ]);
+
+        let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+            .current_dir(dirs.test())
+            .args([
+                "--env-config",
+                "env.nu",
+                "--config",
+                "config.nu",
+                "--check-config",
+            ])
+            .output()
+            .expect("failed to run nu");
+
+        assert_eq!(child_output.status.code(), Some(1));
+        assert!(!child_output.stderr.is_empty());
+    })
+}
+
+#[test]
+fn check_config_fails_for_missing_config() {
+    Playground::setup("check_config_missing", |dirs, _| {
+        let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+            .current_dir(dirs.test())
+            .args(["--config", "missing.nu", "--check-config"])
+            .output()
+            .expect("failed to run nu");
+
+        assert_eq!(child_output.status.code(), Some(1));
+    })
+}
+
+#[test]
+fn profile_startup_writes_report_and_exits() {
+    Playground::setup("profile_startup_exit", |dirs, sandbox| {
+        sandbox.with_files(&[
+            FileWithContent("env.nu", "$env.FOO = 'foo'"),
+            FileWithContent("config.nu", "$env.BAR = [1 2 3] | math sum"),
+        ]);
+
+        let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+            .current_dir(dirs.test())
+            .args([
+                "--env-config",
+                "env.nu",
+                "--config",
+                "config.nu",
+                "--profile-startup",
+                "profile.nuon",
+                "--profile-startup-exit",
+                "-c",
+                "print 'not printed'",
+            ])
+            .output()
+            .expect("failed to run nu");
+
+        assert!(child_output.status.success());
+        assert!(child_output.stdout.is_empty());
+
+        let actual = nu!(
+            cwd: dirs.test(),
+            "open profile.nuon | where source =~ 'math sum' | length | $in > 0"
+        );
+        assert_eq!(actual.out, "true");
+    })
+}
+
+#[test]
+fn profile_startup_exit_fails_if_profile_is_not_written() {
+    Playground::setup("profile_startup_exit_fails", |dirs, _| {
+        let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+            .current_dir(dirs.test())
+            .args([
+                "-n",
+                "--profile-startup",
+                "missing/profile.nuon",
+                "--profile-startup-exit",
+                "-c",
+                "print spam",
+            ])
+            .output()
+            .expect("failed to run nu");
+
+        assert_eq!(child_output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&child_output.stderr).contains("startup profile"));
+    })
+}
+
+#[test]
+fn profile_startup_exit_without_profile_startup_errors() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--profile-startup-exit", "-c", "print spam"])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(!child_output.status.success());
+    assert!(
+        String::from_utf8_lossy(&child_output.stderr)
+            .contains("`--profile-startup-exit` can only be used with `--profile-startup`")
+    );
+}
+
+#[test]
+fn profile_startup_continues_by_default() {
+    Playground::setup("profile_startup_continue", |dirs, _| {
+        let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+            .current_dir(dirs.test())
+            .args(["--profile-startup", "profile.nuon", "-c", "print spam"])
+            .output()
+            .expect("failed to run nu");
+
+        assert!(child_output.status.success());
+        assert_eq!(String::from_utf8_lossy(&child_output.stdout).trim(), "spam");
+        assert!(dirs.test().join("profile.nuon").exists());
+    })
+}
+
+#[test]
+fn include_path_can_be_repeated() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args([
+            "-n",
+            "-I",
+            "a",
+            "--include-path",
+            "b\x1ec",
+            "-I",
+            "d",
+            "-c",
+            "$NU_LIB_DIRS | to nuon",
+        ])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(child_output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&child_output.stdout).trim(),
+        "[a, b, c, d]"
+    );
+}
+
+#[test]
+fn include_path_uses_modules_from_every_directory() {
+    Playground::setup("include_path_repeated", |dirs, sandbox| {
+        sandbox
+            .mkdir("lib1")
+            .mkdir("lib2")
+            .with_files(&[FileWithContent(
+                "lib2/spam.nu",
+                "export def main [] { 'spam' }",
+            )]);
+
+        let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+            .current_dir(dirs.test())
+            .args(["-n", "-I", "lib1", "-I", "lib2", "-c", "use spam.nu; spam"])
+            .output()
+            .expect("failed to run nu");
+
+        assert!(child_output.status.success());
+        assert_eq!(String::from_utf8_lossy(&child_output.stdout).trim(), "spam");
+    })
+}
+
+#[test]
+fn dump_ir_prints_ir_without_running() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--dump-ir", "-c", "print 'not printed'; 1 + 2"])
+        .output()
+        .expect("failed to run nu");
+
+    let stdout = String::from_utf8_lossy(&child_output.stdout);
+    assert!(child_output.status.success());
+    assert!(!stdout.contains("not printed\n"));
+    assert!(stdout.contains("call"));
+    assert!(stdout.contains("return"));
+}
+
+#[test]
+fn dump_ir_reports_parse_errors() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--dump-ir", "-c", "let x = ("])
+        .output()
+        .expect("failed to run nu");
+
+    assert_eq!(child_output.status.code(), Some(1));
+    assert!(!child_output.stderr.is_empty());
+}
+
+#[test]
+fn ast_prints_table_without_running() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--ast", "-c", "print 'not printed'; 1 + 2"])
+        .output()
+        .expect("failed to run nu");
+
+    let stdout = String::from_utf8_lossy(&child_output.stdout);
+    assert!(child_output.status.success());
+    assert!(!stdout.contains("not printed\n"));
+    assert!(stdout.contains("shape_internalcall"));
+    assert!(stdout.contains("shape_operator"));
+}
+
+#[test]
+fn ast_reports_parse_errors() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--ast", "-c", "let x = ("])
+        .output()
+        .expect("failed to run nu");
+
+    assert_eq!(child_output.status.code(), Some(1));
+    assert!(!child_output.stderr.is_empty());
+}
+
 #[test]
 fn main_script_can_have_subcommands1() {
     Playground::setup("main_subcommands", |dirs, sandbox| {
@@ -462,3 +946,60 @@ fn script_file_not_found() {
         "source file for the error was not commandline"
     );
 }
+
+#[test]
+fn table_mode_list_prints_modes() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["-n", "--table-mode", "list", "-c", "print 'not printed'"])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(child_output.status.success());
+    let stdout = String::from_utf8_lossy(&child_output.stdout);
+    let modes: Vec<&str> = stdout.lines().collect();
+    assert!(modes.contains(&"rounded"));
+    assert!(modes.contains(&"heavy"));
+    assert!(!stdout.contains("not printed"));
+}
+
+#[test]
+fn testbin_interruptible_completes_without_interrupt() {
+    let child_output = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["--testbin", "interruptible", "0"])
+        .output()
+        .expect("failed to run nu");
+
+    assert!(child_output.status.success());
+    assert_eq!(
+        "ready\ncompleted\n",
+        String::from_utf8_lossy(&child_output.stdout)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn testbin_interruptible_honors_sigint() {
+    use nix::{
+        sys::signal::{Signal, kill},
+        unistd::Pid,
+    };
+    use std::io::{BufRead, BufReader};
+
+    let mut child = std::process::Command::new(nu_test_support::fs::executable_path())
+        .args(["--testbin", "interruptible", "30"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("failed to run nu");
+
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut line = String::new();
+    stdout.read_line(&mut line).expect("failed to read stdout");
+    assert_eq!(line, "ready\n");
+
+    kill(Pid::from_raw(child.id() as i32), Signal::SIGINT).expect("failed to send SIGINT");
+
+    line.clear();
+    stdout.read_line(&mut line).expect("failed to read stdout");
+    assert_eq!(line, "interrupted\n");
+    assert!(child.wait().expect("failed to wait for nu").success());
+}